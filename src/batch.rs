@@ -0,0 +1,193 @@
+// [[file:../parser.note::*docs][docs:1]]
+//! Parse many files in parallel with a bounded thread pool.
+//!
+//! # Example
+//!
+//! ```
+//! use gchemol_parser::batch::Batch;
+//!
+//! let batch = Batch::glob("./tests/files/*.xyz").unwrap().threads(2);
+//! let results = batch.parse(|reader| Ok(reader.lines().count())).unwrap();
+//! assert_eq!(results.len(), 1);
+//! assert_eq!(results[0].as_ref().unwrap(), &99);
+//! ```
+// docs:1 ends here
+
+// [[file:../parser.note::2d8f4b1a][2d8f4b1a]]
+use crate::reader::TextReader;
+
+use gut::fs::*;
+use gut::prelude::*;
+// 2d8f4b1a ends here
+
+// [[file:../parser.note::7e3c5a96][7e3c5a96]]
+/// Find files matching glob `pattern` recursively, sorted by path. `*`
+/// does not match across directories, whereas `**` does. Symbolic links to
+/// directories are not followed, and unreadable subdirectories are skipped
+/// with a warning.
+fn glob_paths(pattern: &str) -> Result<Vec<PathBuf>> {
+    let glob = globset::GlobBuilder::new(pattern).literal_separator(true).build()?.compile_matcher();
+    // walk from the longest leading path without glob syntax
+    let base: PathBuf = Path::new(pattern)
+        .components()
+        .take_while(|c| !c.as_os_str().to_string_lossy().contains(['*', '?', '[', '{']))
+        .collect();
+    let mut found = vec![];
+    if base.is_file() {
+        if glob.is_match(&base) {
+            found.push(base);
+        }
+        return Ok(found);
+    }
+
+    // no need to go deeper than the pattern, unless with `**`
+    let recursive = pattern.contains("**");
+    let max_depth = Path::new(pattern).components().count();
+    let depth = base.components().count();
+    let base = if base.as_os_str().is_empty() { PathBuf::from(".") } else { base };
+    let entries = std::fs::read_dir(&base).with_context(|| format!("Failed to read dir {:?}", base))?;
+    let mut dirs = vec![(entries, depth + 1)];
+    while let Some((entries, depth)) = dirs.pop() {
+        for entry in entries {
+            let entry = match entry {
+                Ok(entry) => entry,
+                Err(e) => {
+                    warn!("skipped unreadable dir entry: {e}");
+                    continue;
+                }
+            };
+            let path = entry.path();
+            // file type of symbolic link itself, not the target
+            let is_dir = entry.file_type().is_ok_and(|t| t.is_dir());
+            if is_dir {
+                if recursive || depth < max_depth {
+                    match std::fs::read_dir(&path) {
+                        Ok(entries) => dirs.push((entries, depth + 1)),
+                        Err(e) => warn!("skipped unreadable dir {:?}: {e}", path),
+                    }
+                }
+            } else if glob.is_match(&path) || glob.is_match(path.strip_prefix("./").unwrap_or(&path)) {
+                found.push(path);
+            }
+        }
+    }
+    found.sort();
+    Ok(found)
+}
+
+/// A batch of files to be parsed in parallel.
+#[derive(Debug, Clone, Default)]
+pub struct Batch {
+    paths: Vec<PathBuf>,
+    nthreads: usize,
+}
+
+impl Batch {
+    /// Parse files in `paths` in order.
+    pub fn new<P: AsRef<Path>>(paths: &[P]) -> Self {
+        Self {
+            paths: paths.iter().map(|p| p.as_ref().to_owned()).collect(),
+            nthreads: 0,
+        }
+    }
+
+    /// Parse files matching glob `pattern`, such as "data/**/*.log", in the
+    /// order sorted by path.
+    pub fn glob(pattern: &str) -> Result<Self> {
+        let paths = glob_paths(pattern)?;
+        Ok(Self { paths, nthreads: 0 })
+    }
+
+    /// Use at most `n` threads. The default is the number of CPUs.
+    pub fn threads(mut self, n: usize) -> Self {
+        self.nthreads = n;
+        self
+    }
+
+    /// Return the paths of files to parse.
+    pub fn paths(&self) -> &[PathBuf] {
+        &self.paths
+    }
+
+    /// Call `f` on each file path in parallel, which is useful for parsing
+    /// with other readers such as `GrepReader`. Results are in the same
+    /// order as paths, and errors are tagged by file path.
+    pub fn map_files<T, F>(&self, f: F) -> Result<Vec<Result<T>>>
+    where
+        T: Send,
+        F: Fn(&Path) -> Result<T> + Sync,
+    {
+        let pool = gut::rayon::ThreadPoolBuilder::new().num_threads(self.nthreads).build()?;
+        let results = pool.install(|| {
+            self.paths
+                .par_iter()
+                .map(|p| f(p).with_context(|| format!("Failed to parse file {:?}", p)))
+                .collect()
+        });
+        Ok(results)
+    }
+
+    /// Parse each file with `f` receiving a `TextReader` in parallel.
+    /// Results are in the same order as paths, and errors are tagged by file
+    /// path.
+    pub fn parse<T, F>(&self, f: F) -> Result<Vec<Result<T>>>
+    where
+        T: Send,
+        F: Fn(TextReader<BufReader<File>>) -> Result<T> + Sync,
+    {
+        self.map_files(|p| f(TextReader::try_from_path(p)?))
+    }
+}
+// 7e3c5a96 ends here
+
+// [[file:../parser.note::c0a4e7d2][c0a4e7d2]]
+#[test]
+fn test_batch_parse() -> Result<()> {
+    use crate::GrepReader;
+
+    let dir = tempfile::tempdir()?;
+    let root = dir.path();
+    for (i, sub) in ["a", "a/b", "c"].iter().enumerate() {
+        std::fs::create_dir_all(root.join(sub))?;
+        write_to_file(root.join(sub).join(format!("{i}.log")), &"x\n".repeat(i + 1))?;
+        write_to_file(root.join(sub).join("skip.txt"), "")?;
+    }
+    write_to_file(root.join("c/bad.log"), "x\ny\n")?;
+
+    let pattern = format!("{}/**/*.log", root.display());
+    let batch = Batch::glob(&pattern)?.threads(2);
+    assert_eq!(batch.paths().len(), 4);
+    assert!(batch.paths()[0].ends_with("a/0.log"));
+    assert!(batch.paths()[1].ends_with("a/b/1.log"));
+    assert_eq!(Batch::glob(&format!("{}/*/*.log", root.display()))?.paths().len(), 3);
+    let p = root.join("a/0.log");
+    assert_eq!(Batch::glob(&p.to_string_lossy())?.paths(), [p]);
+
+    // symbolic link cycle is not followed
+    #[cfg(unix)]
+    {
+        std::os::unix::fs::symlink(root, root.join("a/b/cycle"))?;
+        assert_eq!(Batch::glob(&pattern)?.paths().len(), 4);
+    }
+
+    let results = batch.parse(|reader| {
+        let lines: Vec<_> = reader.lines().collect();
+        ensure!(lines.iter().all(|line| line == "x"), "unexpected line");
+        Ok(lines.len())
+    })?;
+    assert_eq!(results.len(), 4);
+    assert_eq!(results[0].as_ref().unwrap(), &1);
+    assert_eq!(results[1].as_ref().unwrap(), &2);
+    assert_eq!(results[2].as_ref().unwrap(), &3);
+    let e = results[3].as_ref().unwrap_err();
+    assert!(format!("{e:?}").contains("bad.log"));
+
+    // with grep reader
+    let batch = Batch::new(&["./tests/files/multi.xyz", "./tests/files/lammps-test.dump"]);
+    let results = batch.map_files(|p| GrepReader::try_from_path(p)?.mark(r"^\s*\d+\s*$", None))?;
+    assert_eq!(results[0].as_ref().unwrap(), &6);
+    assert_eq!(results[1].as_ref().unwrap(), &6);
+
+    Ok(())
+}
+// c0a4e7d2 ends here
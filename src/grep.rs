@@ -7,11 +7,11 @@ use std::io::BufReader;
 // imports:1 ends here
 
 // [[file:../parser.note::480b544e][480b544e]]
-mod grep_lib;
-mod grep_bin;
+pub(crate) mod grep_lib;
 // 480b544e ends here
 
 // [[file:../parser.note::b3c30bcf][b3c30bcf]]
+use crate::newline::Newline;
 use crate::view::TextViewer;
 
 use std::io::SeekFrom;
@@ -28,6 +28,12 @@ pub struct GrepReader {
     position_markers: Vec<u64>,
     // current position
     marker_index: usize,
+    // state for incremental marking
+    scan_state: Option<ScanState>,
+    // progress reporting and cancellation
+    monitor: Option<Monitor>,
+    // line ending policy for text read in
+    newline: Newline,
 }
 
 impl GrepReader {
@@ -40,6 +46,9 @@ impl GrepReader {
             src: p.to_owned(),
             position_markers: vec![],
             marker_index: 0,
+            scan_state: None,
+            monitor: None,
+            newline: Newline::default(),
         };
         Ok(grep)
     }
@@ -54,7 +63,17 @@ impl GrepReader {
         use self::grep_lib::mark_matched_positions_with_ripgrep;
 
         let max_count = max_count.into();
-        self.position_markers = mark_matched_positions_with_ripgrep(pattern, &self.src, max_count)?;
+        // file could grow during marking
+        let len = self.reader.get_ref().metadata()?.len();
+        self.position_markers = match &self.monitor {
+            Some(m) => grep_lib::mark_matched_positions_monitored(pattern, &self.src, max_count, m, self.newline)?,
+            None => mark_matched_positions_with_ripgrep(pattern, &self.src, max_count, self.newline)?,
+        };
+        // resuming is possible only when the whole file scanned
+        self.scan_state = match max_count {
+            None => Some(ScanState::new(&self.src, pattern, len, self.newline)?),
+            Some(_) => None,
+        };
 
         self.marker_index = 0;
         Ok(self.position_markers.len())
@@ -62,12 +81,12 @@ impl GrepReader {
 
     /// Goto the start of inner file.
     pub fn goto_start(&mut self) {
-        self.reader.rewind();
+        let _ = self.reader.rewind();
     }
 
     /// Goto the end of inner file.
     pub fn goto_end(&mut self) {
-        self.reader.seek(SeekFrom::End(0));
+        let _ = self.reader.seek(SeekFrom::End(0));
     }
 
     /// Return the number of marked positions.
@@ -89,20 +108,29 @@ impl GrepReader {
         }
     }
 
-    /// Goto the marked position in `marker_index`. Will panic if marker_index
-    /// out of range.
+    /// Goto the marked position in `marker_index`. Return error if
+    /// `marker_index` out of range.
     pub fn goto_marker(&mut self, marker_index: usize) -> Result<u64> {
-        let pos = self.position_markers[marker_index];
+        let n = self.position_markers.len();
+        let &pos = self
+            .position_markers
+            .get(marker_index)
+            .ok_or_else(|| anyhow!("marker index {marker_index} out of range for {n} markers"))?;
         let _ = self.reader.seek(SeekFrom::Start(pos))?;
         self.marker_index = marker_index + 1;
         Ok(pos)
     }
 
+    /// Return current marker index
+    pub fn current_marker(&self) -> usize {
+        self.marker_index
+    }
+
     /// Return `n` lines in string on success from current
     /// position. Return error if reached EOF early.
     pub fn read_lines(&mut self, n: usize, buffer: &mut String) -> Result<()> {
         for i in 0..n {
-            let nbytes = self.reader.read_line(buffer)?;
+            let nbytes = self.newline.read_line(&mut self.reader, buffer)?;
             if nbytes == 0 {
                 bail!("The stream has reached EOF. Required {} lines, but filled {} lines", n, i);
             }
@@ -137,8 +165,8 @@ impl GrepReader {
             let delta = pos_mark - pos_cur;
             let mut nsum = 0;
             for _ in 0.. {
-                let n = self.reader.read_line(s)?;
-                assert_ne!(n, 0);
+                let n = self.newline.read_line(&mut self.reader, s)?;
+                ensure!(n != 0, "reached EOF before next marker: file truncated?");
                 nsum += n as u64;
                 if nsum >= delta {
                     break;
@@ -146,7 +174,7 @@ impl GrepReader {
             }
             self.marker_index += 1;
         } else {
-            while self.reader.read_line(s)? != 0 {
+            while self.newline.read_line(&mut self.reader, s)? != 0 {
                 //
             }
         }
@@ -163,6 +191,605 @@ impl GrepReader {
 }
 // b3c30bcf ends here
 
+// [[file:../parser.note::e41f6c0d][e41f6c0d]]
+use std::ops::{Bound, RangeBounds};
+
+/// Resolve indices of `n` items selected by `range` with `step`. Negative
+/// index counts from the end, like slicing in Python.
+fn slice_indices(n: usize, range: impl RangeBounds<isize>, step: usize) -> Result<Vec<usize>> {
+    ensure!(step > 0, "slice step cannot be zero");
+
+    let n = n as isize;
+    // resolve negative index, shift it by `offset` for the bound, and clamp
+    // it into 0..=n
+    let resolve = |i: isize, offset: isize| if i < 0 { i + n } else { i }.saturating_add(offset).clamp(0, n);
+    let start = match range.start_bound() {
+        Bound::Included(&i) => resolve(i, 0),
+        Bound::Excluded(&i) => resolve(i, 1),
+        Bound::Unbounded => 0,
+    };
+    let stop = match range.end_bound() {
+        Bound::Included(&i) => resolve(i, 1),
+        Bound::Excluded(&i) => resolve(i, 0),
+        Bound::Unbounded => n,
+    };
+    let indices = (start..stop).step_by(step).map(|i| i as usize).collect();
+    Ok(indices)
+}
+
+/// Slicing over marked frames. A frame is the text from a marked position
+/// to the next marked position or the end of file.
+impl GrepReader {
+    /// Return the byte range of frame located at marker `i`.
+    pub(crate) fn frame_span(&self, i: usize) -> Result<(u64, u64)> {
+        let n = self.position_markers.len();
+        ensure!(i < n, "marker index out of range: {i} (total {n})");
+        let beg = self.position_markers[i];
+        let end = match self.position_markers.get(i + 1) {
+            Some(&pos) => pos,
+            None => self.reader.get_ref().metadata()?.len(),
+        };
+        Ok((beg, end))
+    }
+
+    /// Read in text of frame at marker `i` into `buf`, and forward the cursor
+    /// to the next marker.
+    fn read_frame(&mut self, i: usize, buf: &mut String) -> Result<()> {
+        let (beg, end) = self.frame_span(i)?;
+        self.goto_marker(i)?;
+        let start = buf.len();
+        Read::by_ref(&mut self.reader).take(end - beg).read_to_string(buf)?;
+        self.newline.normalize_from(buf, start);
+        Ok(())
+    }
+
+    /// Return marker indices of frames selected by `range` with `step`.
+    /// Negative index in `range` counts from the last marker.
+    pub fn select_frames(&self, range: impl RangeBounds<isize>, step: usize) -> Result<Vec<usize>> {
+        slice_indices(self.position_markers.len(), range, step)
+    }
+
+    /// Returns an iterator over raw text of frames selected by `range` with
+    /// `step`.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use gchemol_parser::GrepReader;
+    ///
+    /// let mut reader = GrepReader::try_from_path("./tests/files/multi.xyz".as_ref()).unwrap();
+    /// reader.mark(r"^\s*\d+\s*$", None).unwrap();
+    /// // the last two frames
+    /// let frames: Vec<_> = reader.read_frames(-2.., 1).unwrap().collect();
+    /// assert_eq!(frames.len(), 2);
+    /// ```
+    pub fn read_frames(
+        &mut self,
+        range: impl RangeBounds<isize>,
+        step: usize,
+    ) -> Result<impl Iterator<Item = Result<String>> + '_> {
+        let indices = self.select_frames(range, step)?;
+        let frames = indices.into_iter().map(move |i| {
+            let mut s = String::new();
+            self.read_frame(i, &mut s)?;
+            Ok(s)
+        });
+        Ok(frames)
+    }
+
+    /// Returns an iterator over frames selected by `range` with `step`, which
+    /// can be viewed like in a normal text viewer.
+    pub fn view_frames(
+        &mut self,
+        range: impl RangeBounds<isize>,
+        step: usize,
+    ) -> Result<impl Iterator<Item = Result<TextViewer>> + '_> {
+        let frames = self.read_frames(range, step)?;
+        Ok(frames.map(|s| Ok(TextViewer::from_str(&s?))))
+    }
+
+    /// Copy raw text of frames selected by `range` with `step` into `w`,
+    /// without collecting text into an intermediate `String`. Return the
+    /// number of bytes copied. Line endings are copied as they are,
+    /// regardless of the newline policy.
+    pub fn copy_frames<W: Write>(&mut self, range: impl RangeBounds<isize>, step: usize, w: &mut W) -> Result<u64> {
+        let mut nbytes = 0;
+        for i in self.select_frames(range, step)? {
+            let (beg, end) = self.frame_span(i)?;
+            self.goto_marker(i)?;
+            nbytes += std::io::copy(&mut Read::by_ref(&mut self.reader).take(end - beg), w)?;
+        }
+        Ok(nbytes)
+    }
+}
+// e41f6c0d ends here
+
+// [[file:../parser.note::8a2d47c3][8a2d47c3]]
+use std::ops::Range;
+
+/// Return the start of the first line beginning at or after position `pos`
+/// in `f`, or the file end if there is no such line.
+fn next_line_start(f: &mut File, pos: u64, newline: Newline) -> Result<u64> {
+    if pos == 0 {
+        return Ok(0);
+    }
+    // read from the byte before `pos` through the end of line
+    f.seek(SeekFrom::Start(pos - 1))?;
+    let mut line = vec![];
+    let n = newline.read_line_bytes(&mut BufReader::new(f), &mut line)?;
+    Ok(pos - 1 + n as u64)
+}
+
+/// Positions marked inside a region of file, which are relative to the
+/// start of the region.
+#[derive(Debug, Clone)]
+pub struct RegionMarkers {
+    region: Range<u64>,
+    position_markers: Vec<u64>,
+}
+
+impl RegionMarkers {
+    /// Return the byte range of the region in file.
+    pub fn region(&self) -> Range<u64> {
+        self.region.clone()
+    }
+
+    /// Return the number of marked positions.
+    pub fn num_markers(&self) -> usize {
+        self.position_markers.len()
+    }
+
+    /// Return marked positions relative to the start of the region.
+    pub fn positions(&self) -> &[u64] {
+        &self.position_markers
+    }
+
+    /// Return the absolute position in file of marker `i`.
+    pub fn absolute_position(&self, i: usize) -> Option<u64> {
+        self.position_markers.get(i).map(|p| p + self.region.start)
+    }
+}
+
+/// Nested search in a region of file.
+impl GrepReader {
+    /// Mark positions that matching `pattern` only inside the byte range
+    /// `region`, without moving the cursor. Regex can be used in `pattern`.
+    /// If `region` starts in the middle of a line, its start is moved forward
+    /// to the start of next line.
+    ///
+    /// # Paramters
+    /// * max_count: exits search if max_count matches reached.
+    pub fn mark_in_region(
+        &self,
+        pattern: &str,
+        region: Range<u64>,
+        max_count: impl Into<Option<usize>>,
+    ) -> Result<RegionMarkers> {
+        use self::grep_lib::mark_matched_positions_in_region;
+
+        ensure!(region.start <= region.end, "invalid region: {:?}", region);
+        let mut f = File::open(&self.src)?;
+        let start = next_line_start(&mut f, region.start, self.newline)?;
+        let region = start.min(region.end)..region.end;
+        let position_markers = mark_matched_positions_in_region(pattern, &self.src, region.clone(), max_count.into(), self.newline)?;
+        Ok(RegionMarkers { region, position_markers })
+    }
+
+    /// Mark positions that matching `pattern` only inside the frame at marker
+    /// `i`, i.e. between marker `i` and the next marker or file end.
+    pub fn mark_in_frame(&self, i: usize, pattern: &str, max_count: impl Into<Option<usize>>) -> Result<RegionMarkers> {
+        let (beg, end) = self.frame_span(i)?;
+        self.mark_in_region(pattern, beg..end, max_count)
+    }
+
+    /// Goto the position of marker `i` in a region marked by
+    /// `mark_in_region` or `mark_in_frame`. Return the absolute position on
+    /// success.
+    pub fn goto_region_marker(&mut self, markers: &RegionMarkers, i: usize) -> Result<u64> {
+        let pos = markers
+            .absolute_position(i)
+            .with_context(|| format!("marker index out of range: {i} (total {})", markers.num_markers()))?;
+        let _ = self.reader.seek(SeekFrom::Start(pos))?;
+        Ok(pos)
+    }
+}
+// 8a2d47c3 ends here
+
+// [[file:../parser.note::1e6b93f5][1e6b93f5]]
+/// The number of leading bytes for detecting rewrite of file.
+const HEAD_SIZE: u64 = 4096;
+
+/// Where the previous marking stopped.
+#[derive(Debug, Clone)]
+struct ScanState {
+    pattern: String,
+    // the end of last complete line scanned
+    scanned: u64,
+    // the leading bytes of file when scanned
+    head: Vec<u8>,
+}
+
+impl ScanState {
+    /// Record the state of scanning file in `path` up to `len` bytes.
+    fn new(path: &Path, pattern: &str, len: u64, newline: Newline) -> Result<Self> {
+        let mut f = File::open(path)?;
+        let scanned = last_line_end(&mut f, 0, len, newline)?;
+        let head = read_head(&mut f, scanned)?;
+        let state = Self {
+            pattern: pattern.to_owned(),
+            scanned,
+            head,
+        };
+        Ok(state)
+    }
+}
+
+/// Read leading bytes of `f`, but no more than `len` bytes.
+fn read_head(f: &mut File, len: u64) -> Result<Vec<u8>> {
+    let mut head = vec![];
+    f.rewind()?;
+    Read::by_ref(f).take(len.min(HEAD_SIZE)).read_to_end(&mut head)?;
+    Ok(head)
+}
+
+/// Return the position after the last line ending according to `newline` in
+/// range `from..len` of `f`, or `from` if there is no line ending.
+fn last_line_end(f: &mut File, from: u64, len: u64, newline: Newline) -> Result<u64> {
+    let mut buf = vec![0; 8192];
+    let mut end = len;
+    while end > from {
+        let beg = end.saturating_sub(buf.len() as u64).max(from);
+        let chunk = &mut buf[..(end - beg) as usize];
+        f.seek(SeekFrom::Start(beg))?;
+        f.read_exact(chunk)?;
+        // a CR in the last byte could be the first half of CRLF
+        let is_end = |i: usize| chunk[i] == b'\n' || (newline.is_eol(chunk[i]) && beg + (i as u64) + 1 < len);
+        if let Some(i) = (0..chunk.len()).rev().find(|&i| is_end(i)) {
+            return Ok(beg + i as u64 + 1);
+        }
+        end = beg;
+    }
+    Ok(from)
+}
+
+/// Incremental marking for append-only growing file.
+impl GrepReader {
+    /// Mark positions that matching `pattern` in the text appended since last
+    /// marking, and append them to marked positions. Return the number of
+    /// newly marked positions.
+    ///
+    /// A partially written line at the end of file is not marked until it
+    /// is completed. A full rescan will be performed if the file has been
+    /// truncated or rewritten, or `pattern` differs from the last marking.
+    /// In this case, the cursor goes back to the first marker.
+    ///
+    /// The file is reopened, as it could be replaced by a new file with the
+    /// same path, such as in log rotation.
+    pub fn remark(&mut self, pattern: &str) -> Result<usize> {
+        use self::grep_lib::mark_matched_positions_in_region;
+
+        let mut f = File::open(&self.src)?;
+        let pos = self.reader.stream_position()?;
+        self.reader = BufReader::new(f.try_clone()?);
+        self.reader.seek(SeekFrom::Start(pos))?;
+        let len = f.metadata()?.len();
+        let state = match &self.scan_state {
+            Some(state) if state.pattern == pattern && len >= state.scanned => state,
+            _ => {
+                debug!("remark: performing a full rescan");
+                return self.mark(pattern, None);
+            }
+        };
+        if read_head(&mut f, state.scanned)? != state.head {
+            debug!("remark: file was rewritten, performing a full rescan");
+            return self.mark(pattern, None);
+        }
+
+        // only scan complete lines
+        let beg = state.scanned;
+        let end = last_line_end(&mut f, beg, len, self.newline)?;
+        // drop markers in incomplete lines scanned last time
+        self.position_markers.retain(|&pos| pos < beg);
+        let n_old = self.position_markers.len();
+        let marked = mark_matched_positions_in_region(pattern, &self.src, beg..end, None, self.newline)?;
+        self.position_markers.extend(marked.into_iter().map(|pos| pos + beg));
+        self.marker_index = self.marker_index.min(self.position_markers.len());
+
+        let head = if state.head.len() as u64 >= HEAD_SIZE {
+            state.head.clone()
+        } else {
+            read_head(&mut f, end)?
+        };
+        self.scan_state = Some(ScanState {
+            pattern: pattern.to_owned(),
+            scanned: end,
+            head,
+        });
+
+        Ok(self.position_markers.len() - n_old)
+    }
+}
+// 1e6b93f5 ends here
+
+// [[file:../parser.note::6f8c02b9][6f8c02b9]]
+use std::sync::Arc;
+
+#[cfg(unix)]
+fn read_at(f: &File, buf: &mut [u8], pos: u64) -> std::io::Result<usize> {
+    use std::os::unix::fs::FileExt;
+    f.read_at(buf, pos)
+}
+
+#[cfg(windows)]
+fn read_at(f: &File, buf: &mut [u8], pos: u64) -> std::io::Result<usize> {
+    use std::os::windows::fs::FileExt;
+    f.seek_read(buf, pos)
+}
+
+/// Fallback with seek and read on a cloned handle, which shares the cursor
+/// with `f`, so reads are serialized with a lock.
+#[cfg(not(any(unix, windows)))]
+fn read_at(f: &File, buf: &mut [u8], pos: u64) -> std::io::Result<usize> {
+    static LOCK: std::sync::Mutex<()> = std::sync::Mutex::new(());
+
+    let _guard = LOCK.lock().unwrap_or_else(|e| e.into_inner());
+    let mut f = f.try_clone()?;
+    f.seek(SeekFrom::Start(pos))?;
+    f.read(buf)
+}
+
+#[derive(Debug)]
+struct IndexInner {
+    src: PathBuf,
+    file: File,
+    len: u64,
+    position_markers: Vec<u64>,
+}
+
+/// An immutable index of marked positions in a file. It is cheap to clone,
+/// and can be shared across threads, each of which reads the file
+/// independently with its own [`MarkerCursor`].
+#[derive(Debug, Clone)]
+pub struct MarkerIndex {
+    inner: Arc<IndexInner>,
+    newline: Newline,
+}
+
+impl MarkerIndex {
+    /// Build an index of `position_markers` for file in path `p`.
+    pub fn new(p: &Path, position_markers: Vec<u64>) -> Result<Self> {
+        let file = File::open(p)?;
+        let len = file.metadata()?.len();
+        ensure!(
+            position_markers.windows(2).all(|w| w[0] <= w[1]),
+            "marked positions should be sorted"
+        );
+        if let Some(&last) = position_markers.last() {
+            ensure!(last <= len, "marked position {last} beyond file end {len}");
+        }
+        let inner = IndexInner {
+            src: p.to_owned(),
+            file,
+            len,
+            position_markers,
+        };
+        Ok(Self {
+            inner: Arc::new(inner),
+            newline: Newline::default(),
+        })
+    }
+
+    /// Set the policy for handling line endings in frames read in.
+    pub fn with_newline(mut self, newline: Newline) -> Self {
+        self.newline = newline;
+        self
+    }
+
+    /// Return the path of indexed file.
+    pub fn path(&self) -> &Path {
+        &self.inner.src
+    }
+
+    /// Return the number of marked positions.
+    pub fn num_markers(&self) -> usize {
+        self.inner.position_markers.len()
+    }
+
+    /// Return all marked positions.
+    pub fn positions(&self) -> &[u64] {
+        &self.inner.position_markers
+    }
+
+    /// Return the byte range of frame at marker `i`, i.e. from marker `i` to
+    /// the next marker or file end.
+    pub fn frame_span(&self, i: usize) -> Result<(u64, u64)> {
+        let markers = &self.inner.position_markers;
+        let n = markers.len();
+        ensure!(i < n, "marker index out of range: {i} (total {n})");
+        let end = markers.get(i + 1).copied().unwrap_or(self.inner.len);
+        Ok((markers[i], end))
+    }
+
+    /// Create a new cursor located at the start of file.
+    pub fn cursor(&self) -> MarkerCursor {
+        MarkerCursor {
+            index: self.clone(),
+            pos: 0,
+        }
+    }
+
+    /// Read in text of frame at marker `i` into `buf`, using positional read
+    /// that does not affect any cursor.
+    pub fn read_frame(&self, i: usize, buf: &mut String) -> Result<()> {
+        let (beg, end) = self.frame_span(i)?;
+        let mut cursor = self.cursor();
+        cursor.pos = beg;
+        let start = buf.len();
+        cursor.take(end - beg).read_to_string(buf)?;
+        self.newline.normalize_from(buf, start);
+        Ok(())
+    }
+}
+
+/// A lightweight cursor over the file of a [`MarkerIndex`], reading with
+/// positional reads, so that many cursors can work on the same file
+/// concurrently.
+#[derive(Debug, Clone)]
+pub struct MarkerCursor {
+    index: MarkerIndex,
+    pos: u64,
+}
+
+impl MarkerCursor {
+    /// Goto the marked position in `marker_index`. Return the position on
+    /// success.
+    pub fn goto_marker(&mut self, marker_index: usize) -> Result<u64> {
+        let (pos, _) = self.index.frame_span(marker_index)?;
+        self.pos = pos;
+        Ok(pos)
+    }
+
+    /// Return current position of cursor.
+    pub fn position(&self) -> u64 {
+        self.pos
+    }
+
+    /// Return a reader over raw bytes of the frame at marker `i`, and forward
+    /// the cursor to the end of the frame while reading.
+    pub fn frame_reader(&mut self, i: usize) -> Result<impl BufRead + '_> {
+        let (beg, end) = self.index.frame_span(i)?;
+        self.pos = beg;
+        Ok(BufReader::new(Read::by_ref(self).take(end - beg)))
+    }
+}
+
+impl Read for MarkerCursor {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        let n = read_at(&self.index.inner.file, buf, self.pos)?;
+        self.pos += n as u64;
+        Ok(n)
+    }
+}
+
+impl Seek for MarkerCursor {
+    fn seek(&mut self, pos: SeekFrom) -> std::io::Result<u64> {
+        let new = match pos {
+            SeekFrom::Start(p) => Some(p),
+            SeekFrom::End(d) => self.index.inner.len.checked_add_signed(d),
+            SeekFrom::Current(d) => self.pos.checked_add_signed(d),
+        };
+        let new = new.ok_or_else(|| std::io::Error::new(std::io::ErrorKind::InvalidInput, "invalid seek position"))?;
+        self.pos = new;
+        Ok(new)
+    }
+}
+
+impl GrepReader {
+    /// Return an immutable index of marked positions, which can be shared
+    /// across threads.
+    pub fn index(&self) -> Result<MarkerIndex> {
+        let index = MarkerIndex::new(&self.src, self.position_markers.clone())?;
+        Ok(index.with_newline(self.newline))
+    }
+}
+// 6f8c02b9 ends here
+
+// [[file:../parser.note::0f6b3d2a][0f6b3d2a]]
+use crate::reader::Checkpoint;
+
+impl GrepReader {
+    /// Return the cursor state for resuming, with the index of next marker
+    /// to visit as the part index.
+    pub fn checkpoint(&mut self) -> Result<Checkpoint> {
+        let position = self.reader.stream_position()?;
+        let checkpoint = Checkpoint {
+            position,
+            part_index: self.marker_index,
+            ..Default::default()
+        };
+        Ok(checkpoint)
+    }
+
+    /// Resume reading from the state in `checkpoint`. The positions should
+    /// be marked in the same way as when saving the state.
+    pub fn resume(&mut self, checkpoint: &Checkpoint) -> Result<()> {
+        let n = self.position_markers.len();
+        let i = checkpoint.part_index;
+        ensure!(i <= n, "invalid marker index {i} in checkpoint for {n} markers");
+        self.reader.seek(SeekFrom::Start(checkpoint.position))?;
+        self.marker_index = i;
+        Ok(())
+    }
+}
+
+impl MarkerCursor {
+    /// Return the cursor state for resuming, with the index of next marker
+    /// not before current position as the part index.
+    pub fn checkpoint(&self) -> Checkpoint {
+        let part_index = self.index.positions().partition_point(|&p| p < self.pos);
+        Checkpoint {
+            position: self.pos,
+            part_index,
+            ..Default::default()
+        }
+    }
+
+    /// Resume reading from the state in `checkpoint`.
+    pub fn resume(&mut self, checkpoint: &Checkpoint) {
+        self.pos = checkpoint.position;
+    }
+}
+// 0f6b3d2a ends here
+
+// [[file:../parser.note::f3a87c25][f3a87c25]]
+use crate::progress::Monitor;
+
+impl GrepReader {
+    /// Report progress and check cancellation with `monitor` in marking.
+    pub fn monitor(&mut self, monitor: &Monitor) {
+        self.monitor = Some(monitor.clone());
+    }
+
+    /// Set the policy for handling line endings in text read in, which is
+    /// also followed in marking: with `Newline::Universal`, a single CR ends
+    /// a line in matching patterns.
+    pub fn newline(&mut self, newline: Newline) {
+        self.newline = newline;
+    }
+}
+// f3a87c25 ends here
+
+// [[file:../parser.note::5e2d8f14][5e2d8f14]]
+/// The number of bytes scanned in each thread for parallel marking.
+const PAR_CHUNK_SIZE: usize = 64 * 1024 * 1024;
+
+impl GrepReader {
+    /// Mark positions that matching `pattern` like [`GrepReader::mark`], but
+    /// scan the file in parallel over a memory map, which is much faster for
+    /// very large files. Return the number of marked positions.
+    ///
+    /// # NOTE
+    /// * The file should not be modified during marking.
+    pub fn mark_parallel(&mut self, pattern: &str, max_count: impl Into<Option<usize>>) -> Result<usize> {
+        self.mark_parallel_in_chunks(pattern, max_count.into(), PAR_CHUNK_SIZE)
+    }
+
+    fn mark_parallel_in_chunks(&mut self, pattern: &str, max_count: Option<usize>, chunk_size: usize) -> Result<usize> {
+        use self::grep_lib::mark_matched_positions_parallel;
+
+        let len = self.reader.get_ref().metadata()?.len();
+        self.position_markers = mark_matched_positions_parallel(pattern, &self.src, max_count, chunk_size, self.newline)?;
+        self.scan_state = match max_count {
+            None => Some(ScanState::new(&self.src, pattern, len, self.newline)?),
+            Some(_) => None,
+        };
+
+        self.marker_index = 0;
+        Ok(self.position_markers.len())
+    }
+}
+// 5e2d8f14 ends here
+
 // [[file:../parser.note::3da52855][3da52855]]
 #[test]
 fn test_grep() -> Result<()> {
@@ -192,6 +819,99 @@ fn test_grep() -> Result<()> {
     Ok(())
 }
 
+#[test]
+fn test_grep_parallel() -> Result<()> {
+    let path = "./tests/files/lammps-test.dump";
+    let mut reader = GrepReader::try_from_path(path.as_ref())?;
+    reader.mark("^ITEM: TIMESTEP|^ITEM: BOX", None)?;
+    let markers = reader.position_markers.clone();
+    assert_eq!(markers.len(), 6);
+    // small chunks: header lines straddle range boundaries
+    for chunk_size in [1, 7, 100, 4096, 1 << 30] {
+        let n = reader.mark_parallel_in_chunks("^ITEM: TIMESTEP|^ITEM: BOX", None, chunk_size)?;
+        assert_eq!(n, 6);
+        assert_eq!(reader.position_markers, markers);
+        // the first markers in file order
+        for m in [0, 1, 3, 5, 7] {
+            let n = reader.mark_parallel_in_chunks("^ITEM: TIMESTEP|^ITEM: BOX", Some(m), chunk_size)?;
+            assert_eq!(n, m.min(6));
+            assert_eq!(reader.position_markers, markers[..m.min(6)]);
+        }
+    }
+    assert_eq!(reader.mark_parallel("^ITEM: TIMESTEP", 2)?, 2);
+    // timestep and number of atoms in each frame
+    assert_eq!(reader.mark_parallel(r"^\s*\d+\s*$", None)?, 6);
+
+    // CRLF and single CR straddle range boundaries
+    let dir = gut::fs::tempfile::tempdir()?;
+    let f = dir.path().join("mixed.txt");
+    std::fs::write(&f, "b\r\nab\rb\n\rb\r\r\nb")?;
+    let mut reader = GrepReader::try_from_path(&f)?;
+    reader.newline(Newline::Universal);
+    assert_eq!(reader.mark("^b", None)?, 4);
+    assert_eq!(reader.position_markers, [0, 6, 9, 13]);
+    for chunk_size in [1, 2, 3, 5, 100] {
+        assert_eq!(reader.mark_parallel_in_chunks("^b", None, chunk_size)?, 4);
+        assert_eq!(reader.position_markers, [0, 6, 9, 13]);
+    }
+
+    // works with marker api
+    let path = "./tests/files/multi.xyz";
+    let mut reader = GrepReader::try_from_path(path.as_ref())?;
+    let n = reader.mark_parallel_in_chunks(r"^\s*\d+\s*$", None, 50)?;
+    assert_eq!(n, 6);
+    let _ = reader.goto_marker(4)?;
+    let mut s = String::new();
+    reader.read_lines(1, &mut s)?;
+    assert_eq!(s.trim(), "16");
+    assert_eq!(reader.remark(r"^\s*\d+\s*$")?, 0);
+    assert_eq!(reader.index()?.num_markers(), 6);
+
+    Ok(())
+}
+
+#[test]
+fn test_grep_resume() -> Result<()> {
+    let path = "./tests/files/multi.xyz";
+    let pattern = r"^\s*\d+\s*$";
+    let mut reader = GrepReader::try_from_path(path.as_ref())?;
+    reader.mark(pattern, None)?;
+    let _ = reader.goto_next_marker()?;
+    let _ = reader.goto_next_marker()?;
+    let cp = reader.checkpoint()?;
+    assert_eq!(cp.part_index, 2);
+    let json = serde_json::to_string(&cp)?;
+
+    let mut reader = GrepReader::try_from_path(path.as_ref())?;
+    reader.mark(pattern, None)?;
+    reader.resume(&serde_json::from_str(&json)?)?;
+    let mut s = String::new();
+    reader.read_lines(1, &mut s)?;
+    assert_eq!(s.trim(), "10");
+    let _ = reader.goto_next_marker()?;
+    assert_eq!(reader.current_marker(), 3);
+
+    let index = reader.index()?;
+    let mut cursor = index.cursor();
+    let _ = cursor.goto_marker(4)?;
+    let cp = cursor.checkpoint();
+    assert_eq!(cp.part_index, 4);
+    let mut cursor = index.cursor();
+    cursor.resume(&cp);
+    let mut s = String::new();
+    cursor.frame_reader(4)?.read_line(&mut s)?;
+    assert_eq!(s.trim(), "16");
+
+    let mut reader = TextReader::try_from_path(path.as_ref())?;
+    reader.resume(&cp)?;
+    let mut s = String::new();
+    reader.read_line(&mut s)?;
+    assert_eq!(s.trim(), "16");
+    assert_eq!(reader.checkpoint()?.position, cp.position + s.len() as u64);
+
+    Ok(())
+}
+
 #[test]
 fn test_grep_read_until() -> Result<()> {
     let path = "./tests/files/multi.xyz";
@@ -216,4 +936,211 @@ fn test_grep_read_until() -> Result<()> {
 
     Ok(())
 }
+
+#[test]
+fn test_grep_slice_frames() -> Result<()> {
+    assert_eq!(slice_indices(6, .., 1)?, vec![0, 1, 2, 3, 4, 5]);
+    assert_eq!(slice_indices(6, 1..5, 2)?, vec![1, 3]);
+    assert_eq!(slice_indices(6, -2.., 1)?, vec![4, 5]);
+    assert_eq!(slice_indices(6, ..-4, 1)?, vec![0, 1]);
+    assert_eq!(slice_indices(6, -100..=2, 1)?, vec![0, 1, 2]);
+    assert!(slice_indices(6, -2..1, 1)?.is_empty());
+    assert_eq!(slice_indices(6, 5..100, 1)?, vec![5]);
+    assert!(slice_indices(6, ..=-7, 1)?.is_empty());
+    assert!(slice_indices(6, ..=-100, 1)?.is_empty());
+    assert_eq!(slice_indices(6, ..=-6, 1)?, vec![0]);
+    assert_eq!(slice_indices(6, ..=-1, 1)?, vec![0, 1, 2, 3, 4, 5]);
+    let excluded = (Bound::Excluded(-100), Bound::Excluded(2));
+    assert_eq!(slice_indices(6, excluded, 1)?, vec![0, 1]);
+    let excluded = (Bound::Excluded(-1), Bound::Unbounded);
+    assert!(slice_indices(6, excluded, 1)?.is_empty());
+    assert!(slice_indices(6, .., 0).is_err());
+
+    let path = "./tests/files/multi.xyz";
+    let mut reader = GrepReader::try_from_path(path.as_ref())?;
+    let n = reader.mark(r"^\s*\d+\s*$", None)?;
+    assert_eq!(n, 6);
+
+    let frames: Vec<_> = reader.read_frames(.., 2)?.collect::<Result<_>>()?;
+    assert_eq!(frames.len(), 3);
+    assert_eq!(frames[0].lines().count(), 18);
+    assert!(frames[1].starts_with("          16\r\n Configuration number :       21"));
+    assert_eq!(reader.current_marker(), 5);
+
+    let frames: Vec<_> = reader.view_frames(-1.., 1)?.collect::<Result<_>>()?;
+    assert_eq!(frames.len(), 1);
+    assert_eq!(frames[0].num_lines(), 16);
+
+    // copy all frames reproduces the whole file
+    let mut out = vec![];
+    let nbytes = reader.copy_frames(.., 1, &mut out)?;
+    let txt = gut::fs::read_file(path)?;
+    assert_eq!(nbytes as usize, txt.len());
+    assert_eq!(out, txt.as_bytes());
+
+    Ok(())
+}
+
+#[test]
+fn test_grep_nested() -> Result<()> {
+    let path = "./tests/files/lammps-test.dump";
+    let mut reader = GrepReader::try_from_path(path.as_ref())?;
+    let n = reader.mark(r"^ITEM: TIMESTEP", None)?;
+    assert_eq!(n, 3);
+
+    let sub = reader.mark_in_frame(1, r"^ITEM: NUMBER OF ATOMS|^ITEM: ATOMS", None)?;
+    assert_eq!(sub.num_markers(), 2);
+    let (beg, end) = reader.frame_span(1)?;
+    assert_eq!(sub.region(), beg..end);
+    // "ITEM: TIMESTEP\n100\n"
+    assert_eq!(sub.positions()[0], 19);
+
+    let mut s = String::new();
+    reader.goto_region_marker(&sub, 0)?;
+    reader.read_lines(2, &mut s)?;
+    assert_eq!(s, "ITEM: NUMBER OF ATOMS\n537\n");
+    assert!(reader.goto_region_marker(&sub, 2).is_err());
+
+    // the main markers are untouched
+    s.clear();
+    reader.goto_next_marker()?;
+    reader.read_lines(2, &mut s)?;
+    assert_eq!(s, "ITEM: TIMESTEP\n0\n");
+
+    // restricted search in the last frame
+    let sub = reader.mark_in_frame(2, r"^ITEM: TIMESTEP", None)?;
+    assert_eq!(sub.num_markers(), 1);
+    assert_eq!(sub.positions()[0], 0);
+    let sub = reader.mark_in_region(r"^ITEM: TIMESTEP", 1..end, None)?;
+    assert_eq!(sub.num_markers(), 1);
+
+    // region starting in the middle of a line
+    let sub = reader.mark_in_region(r"^TIMESTEP|^ITEM", 6..end, None)?;
+    // "ITEM: TIMESTEP\n"
+    assert_eq!(sub.region().start, 15);
+    // "0\n"
+    assert_eq!(sub.positions()[0], 2);
+    assert_eq!(sub.absolute_position(0), Some(17));
+    let sub = reader.mark_in_region(r"^TIMESTEP", 6..10, None)?;
+    assert_eq!(sub.region(), 10..10);
+    assert_eq!(sub.num_markers(), 0);
+    let sub = reader.mark_in_region(r"^0", 15..20, None)?;
+    assert_eq!(sub.region(), 15..20);
+    assert_eq!(sub.positions(), [0]);
+    assert!(reader.mark_in_frame(3, r"^ITEM: TIMESTEP", None).is_err());
+
+    Ok(())
+}
+
+#[test]
+fn test_grep_remark() -> Result<()> {
+    use std::fs::OpenOptions;
+
+    let txt = gut::fs::read_file("./tests/files/lammps-test.dump")?;
+    let frames: Vec<_> = txt.match_indices("ITEM: TIMESTEP").map(|(i, _)| i).collect();
+    assert_eq!(frames.len(), 3);
+
+    let dir = gut::fs::tempfile::tempdir()?;
+    let path = dir.path().join("growing.dump");
+    let append = |s: &str| -> Result<()> {
+        let mut f = OpenOptions::new().create(true).append(true).open(&path)?;
+        f.write_all(s.as_bytes())?;
+        Ok(())
+    };
+
+    // the second header is partially written
+    let pattern = r"^ITEM: TIMESTEP$";
+    append(&txt[..frames[1] + 8])?;
+    let mut reader = GrepReader::try_from_path(&path)?;
+    assert_eq!(reader.remark(pattern)?, 1);
+    assert_eq!(reader.remark(pattern)?, 0);
+
+    // complete the second header
+    append(&txt[frames[1] + 8..frames[2] + 15])?;
+    assert_eq!(reader.remark(pattern)?, 2);
+    append(&txt[frames[2] + 15..])?;
+    assert_eq!(reader.remark(pattern)?, 0);
+    assert_eq!(reader.num_markers(), 3);
+    assert_eq!(reader.position_markers, frames.iter().map(|&i| i as u64).collect_vec());
+    let mut s = String::new();
+    reader.goto_marker(2)?;
+    reader.read_until_next_marker(&mut s)?;
+    assert_eq!(s, &txt[frames[2]..]);
+
+    // a new pattern requires full rescan
+    assert_eq!(reader.remark(r"^ITEM: NUMBER OF ATOMS")?, 3);
+
+    // truncated file
+    std::fs::write(&path, &txt[..frames[1]])?;
+    assert_eq!(reader.remark(pattern)?, 1);
+    assert_eq!(reader.num_markers(), 1);
+
+    // rewritten file
+    std::fs::write(&path, txt.replacen("ITEM: TIMESTEP\n0", "ITEM: TIMESTEP\n9", 1))?;
+    assert_eq!(reader.remark(pattern)?, 3);
+
+    // replaced with a new file in the same path
+    std::fs::write(&path, &txt[..frames[2]])?;
+    assert_eq!(reader.remark(pattern)?, 2);
+    let new = dir.path().join("new.dump");
+    std::fs::write(&new, &txt)?;
+    std::fs::rename(&new, &path)?;
+    assert_eq!(reader.remark(pattern)?, 1);
+    s.clear();
+    reader.goto_marker(2)?;
+    reader.read_until_next_marker(&mut s)?;
+    assert_eq!(s, &txt[frames[2]..]);
+
+    Ok(())
+}
+
+#[test]
+fn test_grep_index() -> Result<()> {
+    fn is_send_sync<T: Send + Sync + Clone>() {}
+    is_send_sync::<MarkerIndex>();
+
+    let path = "./tests/files/multi.xyz";
+    let mut reader = GrepReader::try_from_path(path.as_ref())?;
+    reader.mark(r"^\s*\d+\s*$", None)?;
+    let frames: Vec<_> = reader.read_frames(.., 1)?.collect::<Result<_>>()?;
+
+    let index = reader.index()?;
+    assert_eq!(index.num_markers(), 6);
+    let handles: Vec<_> = (0..3)
+        .map(|k| {
+            let index = index.clone();
+            std::thread::spawn(move || {
+                let mut cursor = index.cursor();
+                let mut parts = vec![];
+                for i in (k..6).step_by(3).rev() {
+                    let mut s = String::new();
+                    cursor.frame_reader(i).unwrap().read_to_string(&mut s).unwrap();
+                    parts.push((i, s));
+                }
+                parts
+            })
+        })
+        .collect();
+    for h in handles {
+        for (i, s) in h.join().unwrap() {
+            assert_eq!(s, frames[i]);
+        }
+    }
+
+    let mut s = String::new();
+    index.read_frame(5, &mut s)?;
+    assert_eq!(s, frames[5]);
+    assert!(index.read_frame(6, &mut s).is_err());
+
+    let mut cursor = index.cursor();
+    cursor.goto_marker(1)?;
+    let mut line = String::new();
+    BufReader::new(&mut cursor).read_line(&mut line)?;
+    assert_eq!(line, "          10\r\n");
+    cursor.seek(SeekFrom::End(-2))?;
+    assert_eq!(cursor.position(), index.frame_span(5)?.1 - 2);
+    assert!(cursor.seek(SeekFrom::Current(-1000000)).is_err());
+
+    Ok(())
+}
 // 3da52855 ends here
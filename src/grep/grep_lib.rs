@@ -5,13 +5,6 @@ use super::*;
 // [[file:../../parser.note::aba05bc2][aba05bc2]]
 use ::grep::regex::{RegexMatcher, RegexMatcherBuilder};
 
-// Line oriented matches span no more than one line. The given pattern should
-// not contain a literal \n.
-fn make_matcher(pat: &str) -> Result<RegexMatcher> {
-    let matcher = RegexMatcher::new_line_matcher(&pat)?;
-    Ok(matcher)
-}
-
 // Build a new matcher from a plain alternation of literals, substantially
 // faster than by joining the patterns with a | and calling build.
 fn build_matcher_for_literals<B: AsRef<str>>(literals: &[B]) -> Result<RegexMatcher> {
@@ -44,11 +37,11 @@ where
     type Error = std::io::Error;
 
     fn matched(&mut self, _searcher: &Searcher, mat: &SinkMatch<'_>) -> std::io::Result<bool> {
-        let matched_line = std::str::from_utf8(mat.bytes()).map_err(|e| Self::Error::error_message(e))?;
+        let matched_line = std::str::from_utf8(mat.bytes()).map_err(Self::Error::error_message)?;
         // the absolute byte offset of the start of this match relative to the
         // very beginning of the input.
         let matched_line_position = mat.absolute_byte_offset();
-        (self.0)(matched_line_position, &matched_line)
+        (self.0)(matched_line_position, matched_line)
     }
 }
 // f1d2704d ends here
@@ -64,28 +57,107 @@ fn make_searcher() -> Searcher {
         .build()
 }
 
+/// Convert a single CR in `bytes` into LF in place, so that it ends a line
+/// in searching, without changing byte offsets. `next` is the byte following
+/// `bytes`, if any.
+fn convert_single_cr(bytes: &mut [u8], next: Option<u8>) {
+    for i in 0..bytes.len() {
+        if bytes[i] == b'\r' && bytes.get(i + 1).copied().or(next) != Some(b'\n') {
+            bytes[i] = b'\n';
+        }
+    }
+}
+
+/// A reader converting a single CR into LF in text from `inner`.
+struct SingleCrToLf<R> {
+    inner: R,
+    // the byte read ahead for checking CRLF
+    pending: Option<u8>,
+}
+
+impl<R: Read> SingleCrToLf<R> {
+    fn new(inner: R) -> Self {
+        Self { inner, pending: None }
+    }
+}
+
+impl<R: Read> Read for SingleCrToLf<R> {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        if buf.is_empty() {
+            return Ok(0);
+        }
+        let mut n = 0;
+        if let Some(b) = self.pending.take() {
+            buf[0] = b;
+            n = 1;
+        }
+        match self.inner.read(&mut buf[n..]) {
+            Ok(m) => n += m,
+            Err(e) => {
+                self.pending = buf[..n].first().copied();
+                return Err(e);
+            }
+        }
+        if n == 0 {
+            return Ok(0);
+        }
+        // read ahead one byte if CR is the last one
+        let mut next = None;
+        if buf[n - 1] == b'\r' {
+            let mut b = [0];
+            if self.inner.read(&mut b)? > 0 {
+                next = Some(b[0]);
+                self.pending = next;
+            }
+        }
+        convert_single_cr(&mut buf[..n], next);
+        Ok(n)
+    }
+}
+
+/// Search `reader` with `matcher`, treating a single CR as line ending if
+/// `newline` is `Universal`.
+fn search_reader<R: Read, F>(matcher: RegexMatcher, reader: R, newline: Newline, sink: PartSink<F>) -> Result<()>
+where
+    F: FnMut(u64, &str) -> std::io::Result<bool>,
+{
+    if newline == Newline::Universal {
+        make_searcher().search_reader(matcher, SingleCrToLf::new(reader), sink)?;
+    } else {
+        make_searcher().search_reader(matcher, reader, sink)?;
+    }
+    Ok(())
+}
+
 /// Mark positions with `pattern` using external ripgrep command.
 ///
 /// # Parameters
 /// * max_count: exits search if max_count matches reached.
-pub fn mark_matched_positions_with_ripgrep(pattern: &str, path: &Path, max_count: Option<usize>) -> Result<Vec<u64>> {
-    use std::fs::File;
-    use std::io::prelude::*;
-    use std::io::BufReader;
+/// * newline: a single CR ends a line in matching if `Universal`.
+pub fn mark_matched_positions_with_ripgrep(pattern: &str, path: &Path, max_count: Option<usize>, newline: Newline) -> Result<Vec<u64>> {
+    let reader = BufReader::new(File::open(path)?);
+    mark_matched_positions_in_reader(pattern, reader, max_count, newline)
+}
 
+/// Mark positions with `pattern` in text from `reader`. The positions are
+/// relative to the start of `reader`.
+///
+/// # Parameters
+/// * max_count: exits search if max_count matches reached.
+/// * newline: a single CR ends a line in matching if `Universal`.
+pub fn mark_matched_positions_in_reader<R: Read>(pattern: &str, reader: R, max_count: Option<usize>, newline: Newline) -> Result<Vec<u64>> {
     let max_count = max_count.unwrap_or(usize::MAX);
 
-    let mut reader = BufReader::new(File::open(path)?);
-
     // faster pattern match for regex '|'
     let patterns: Vec<_> = pattern.split("|").collect();
     let matcher = build_matcher_for_literals(&patterns)?;
 
     let mut n = 0;
     let mut marked = vec![];
-    make_searcher().search_reader(
+    search_reader(
         matcher,
-        &mut reader,
+        reader,
+        newline,
         PartSink(|pos, _line| {
             marked.push(pos);
             n += 1;
@@ -94,4 +166,182 @@ pub fn mark_matched_positions_with_ripgrep(pattern: &str, path: &Path, max_count
     )?;
     Ok(marked)
 }
+
+/// Mark positions with `pattern` in byte range `region` of file in `path`.
+/// The positions are relative to the start of `region`.
+///
+/// # NOTE
+/// * `region` should start at the beginning of a line, otherwise `^` in
+///   `pattern` matches in the middle of the line.
+pub fn mark_matched_positions_in_region(
+    pattern: &str,
+    path: &Path,
+    region: std::ops::Range<u64>,
+    max_count: Option<usize>,
+    newline: Newline,
+) -> Result<Vec<u64>> {
+    use std::io::SeekFrom;
+
+    let mut f = File::open(path)?;
+    f.seek(SeekFrom::Start(region.start))?;
+    let reader = BufReader::new(f.take(region.end.saturating_sub(region.start)));
+    mark_matched_positions_in_reader(pattern, reader, max_count, newline)
+}
+
+/// Mark positions with `pattern` in file from `path`, reporting progress
+/// and checking cancellation with `monitor`.
+pub fn mark_matched_positions_monitored(
+    pattern: &str,
+    path: &Path,
+    max_count: Option<usize>,
+    monitor: &crate::progress::Monitor,
+    newline: Newline,
+) -> Result<Vec<u64>> {
+    use crate::progress::Monitored;
+
+    let f = File::open(path)?;
+    monitor.set_total(Some(f.metadata()?.len()));
+    let reader = BufReader::new(Monitored::new(f, monitor));
+    let max_count = max_count.unwrap_or(usize::MAX);
+    let patterns: Vec<_> = pattern.split("|").collect();
+    let matcher = build_matcher_for_literals(&patterns)?;
+
+    let mut marked = vec![];
+    search_reader(
+        matcher,
+        reader,
+        newline,
+        PartSink(|pos, _line| {
+            marked.push(pos);
+            monitor.add_part();
+            Ok(marked.len() < max_count)
+        }),
+    )?;
+    Ok(marked)
+}
 // ca7a00d2 ends here
+
+// [[file:../../parser.note::9a4c0b57][9a4c0b57]]
+use memmap2::Mmap;
+
+/// Split `bytes` into ranges in about `chunk_size` bytes. Each range ends
+/// at a line ending according to `newline`, so that no line straddles a
+/// range boundary.
+fn line_aligned_ranges(bytes: &[u8], chunk_size: usize, newline: Newline) -> Vec<std::ops::Range<usize>> {
+    let chunk_size = chunk_size.max(1);
+    let mut ranges = vec![];
+    let mut start = 0;
+    while start < bytes.len() {
+        let end = (start + chunk_size).min(bytes.len());
+        // extend to the end of the line straddling the boundary
+        let mut end = match bytes[end - 1..].iter().position(|&b| newline.is_eol(b)) {
+            Some(i) => end + i,
+            None => bytes.len(),
+        };
+        // keep CRLF in the same range
+        if bytes[end - 1] == b'\r' && bytes.get(end) == Some(&b'\n') {
+            end += 1;
+        }
+        ranges.push(start..end);
+        start = end;
+    }
+    ranges
+}
+
+/// Mark positions with `pattern` in file from `path` in parallel. The file
+/// is memory-mapped and scanned in ranges in about `chunk_size` bytes on
+/// multiple threads.
+///
+/// # Parameters
+/// * max_count: exits search if max_count matches reached.
+/// * newline: a single CR ends a line in matching if `Universal`.
+///
+/// # NOTE
+/// * The file should not be modified during marking.
+/// * Binary data only stops the scan within the range where NUL byte found.
+pub fn mark_matched_positions_parallel(
+    pattern: &str,
+    path: &Path,
+    max_count: Option<usize>,
+    chunk_size: usize,
+    newline: Newline,
+) -> Result<Vec<u64>> {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    let max_count = max_count.unwrap_or(usize::MAX);
+    let f = File::open(path)?;
+    if f.metadata()?.len() == 0 {
+        return Ok(vec![]);
+    }
+    let mmap = unsafe { Mmap::map(&f)? };
+    let bytes = &mmap[..];
+
+    let patterns: Vec<_> = pattern.split("|").collect();
+    let matcher = build_matcher_for_literals(&patterns)?;
+    let ranges = line_aligned_ranges(bytes, chunk_size, newline);
+    // The number of matches found in each range. The scan in a range stops
+    // when the first `max_count` matches are all found in the range and the
+    // ranges before it.
+    let counts: Vec<_> = ranges.iter().map(|_| AtomicUsize::new(0)).collect();
+    let enough = |i: usize| counts[..=i].iter().map(|c| c.load(Ordering::Relaxed)).sum::<usize>() >= max_count;
+    let found: Vec<Vec<u64>> = ranges
+        .into_par_iter()
+        .enumerate()
+        .map(|(i, range)| {
+            let offset = range.start as u64;
+            let mut marked = vec![];
+            if enough(i) {
+                return Ok(marked);
+            }
+            // a copy is required for converting single CR
+            let chunk: std::borrow::Cow<[u8]> = if newline == Newline::Universal {
+                let mut chunk = bytes[range.clone()].to_vec();
+                convert_single_cr(&mut chunk, bytes.get(range.end).copied());
+                chunk.into()
+            } else {
+                bytes[range].into()
+            };
+            make_searcher().search_slice(
+                matcher.clone(),
+                &chunk,
+                PartSink(|pos, _line| {
+                    marked.push(offset + pos);
+                    counts[i].fetch_add(1, Ordering::Relaxed);
+                    Ok(!enough(i))
+                }),
+            )?;
+            Ok(marked)
+        })
+        .collect::<Result<_>>()?;
+
+    let mut marked = found.concat();
+    marked.truncate(max_count);
+    Ok(marked)
+}
+// 9a4c0b57 ends here
+
+// [[file:../../parser.note::c4e07b19][c4e07b19]]
+#[test]
+fn test_single_cr_to_lf() -> Result<()> {
+    let txt = "a\r\nb\rc\r\r\nd\r";
+    // CR at the end of each read
+    let mut r = SingleCrToLf::new(BufReader::with_capacity(1, txt.as_bytes()));
+    let mut buf = [0; 2];
+    let mut s = vec![];
+    loop {
+        let n = r.read(&mut buf)?;
+        if n == 0 {
+            break;
+        }
+        s.extend_from_slice(&buf[..n]);
+    }
+    assert_eq!(s, b"a\r\nb\nc\n\r\nd\n");
+
+    let positions = mark_matched_positions_in_reader("^[bcd]$", txt.as_bytes(), None, Newline::Universal)?;
+    assert_eq!(positions, [3, 5, 9]);
+    let positions = mark_matched_positions_in_reader("^[bcd]$", txt.as_bytes(), None, Newline::Preserve)?;
+    assert!(positions.is_empty());
+
+    Ok(())
+}
+// c4e07b19 ends here
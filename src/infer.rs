@@ -0,0 +1,328 @@
+// [[file:../parser.note::*docs][docs:1]]
+//! Infer frame boundaries for text in unknown formats.
+//!
+//! # Example
+//!
+//! ```
+//! use gchemol_parser::TextReader;
+//! use gchemol_parser::infer::FrameLayout;
+//!
+//! let f = "./tests/files/multi.xyz";
+//! let mut reader = TextReader::try_from_path(f.as_ref()).unwrap();
+//! let found = reader.infer_frames().unwrap().expect("xyz frames");
+//! assert_eq!(found.layout, FrameLayout::CountHeader { extra: 1 });
+//!
+//! let parts = reader.partitions(found.layout.partitioner().unwrap());
+//! assert_eq!(parts.count(), 6);
+//! ```
+// docs:1 ends here
+
+// [[file:../parser.note::0d5a7c1e][0d5a7c1e]]
+use crate::partition::{BlankSeparated, CountHeader, ReadAction, ReadContext, ReadPart};
+use crate::reader::TextReader;
+use crate::view::TextViewer;
+
+use gut::prelude::*;
+use regex::Regex;
+use std::collections::HashMap;
+use std::io::{BufRead, Seek};
+// 0d5a7c1e ends here
+
+// [[file:../parser.note::b4e19f07][b4e19f07]]
+/// The number of lines sampled from the beginning of text for inference.
+const SAMPLE_LINES: usize = 5000;
+
+/// The repeating record structure found in text.
+#[derive(Debug, Clone, PartialEq)]
+pub enum FrameLayout {
+    /// Each frame starts with a line matching the regex pattern.
+    Header(String),
+    /// Each frame starts with a count line, which gives the number of lines
+    /// followed, plus `extra` lines, such as the comment line in xyz format.
+    CountHeader {
+        /// The number of extra lines between count line and counted lines.
+        extra: usize,
+    },
+    /// Frames are separated by blank lines, as in pxyz format.
+    BlankSeparated,
+}
+
+/// The frame layout inferred with a confidence score.
+#[derive(Debug, Clone, PartialEq)]
+pub struct FrameInference {
+    /// The inferred frame layout.
+    pub layout: FrameLayout,
+    /// The confidence score between 0 and 1.
+    pub confidence: f64,
+    /// The number of frames found in sampled text.
+    pub nframes: usize,
+}
+
+impl FrameLayout {
+    /// Return a regex pattern matching frame header line, which can be used
+    /// in `GrepReader::mark`.
+    pub fn grep_pattern(&self) -> Option<&str> {
+        match self {
+            Self::Header(pattern) => Some(pattern),
+            _ => None,
+        }
+    }
+
+    /// Return a partitioner for reading frames in this layout.
+    pub fn partitioner(&self) -> Result<FramePartition> {
+        let part = match self {
+            Self::Header(pattern) => FramePartition::Header(Regex::new(pattern)?),
+            Self::CountHeader { extra } => FramePartition::CountHeader(CountHeader { line: 1, extra: *extra }),
+            Self::BlankSeparated => FramePartition::BlankSeparated(BlankSeparated),
+        };
+        Ok(part)
+    }
+}
+// b4e19f07 ends here
+
+// [[file:../parser.note::2c8e6a93][2c8e6a93]]
+/// Partitioner for frames in a layout found in [`FrameLayout`].
+#[derive(Debug, Clone)]
+pub enum FramePartition {
+    /// Preceded with a header line matching the regex.
+    Header(Regex),
+    /// Count line plus extra lines.
+    CountHeader(CountHeader),
+    /// Separated by blank lines.
+    BlankSeparated(BlankSeparated),
+}
+
+impl ReadPart for FramePartition {
+    fn read_next(&self, context: ReadContext) -> ReadAction {
+        match self {
+            Self::Header(re) => {
+                let n = context.number_of_lines();
+                if n > 1 && re.is_match(context.line(n)) {
+                    ReadAction::Done(n - 1)
+                } else {
+                    ReadAction::Need(1)
+                }
+            }
+            Self::CountHeader(p) => p.read_next(context),
+            Self::BlankSeparated(p) => p.read_next(context),
+        }
+    }
+
+    fn n_stride(&self) -> usize {
+        match self {
+            Self::Header(_) => 1,
+            Self::CountHeader(p) => p.n_stride(),
+            Self::BlankSeparated(p) => p.n_stride(),
+        }
+    }
+}
+// 2c8e6a93 ends here
+
+// [[file:../parser.note::7a31f4c8][7a31f4c8]]
+/// Score how regular the spacing between frame starts is: 1 for frames in
+/// the same length.
+fn regularity(starts: &[usize]) -> f64 {
+    let gaps: Vec<_> = starts.windows(2).map(|w| (w[1] - w[0]) as f64).collect();
+    if gaps.is_empty() {
+        return 0.0;
+    }
+    let mean = gaps.iter().sum::<f64>() / gaps.len() as f64;
+    let var = gaps.iter().map(|x| (x - mean).powi(2)).sum::<f64>() / gaps.len() as f64;
+    (1.0 - var.sqrt() / mean).max(0.0)
+}
+
+/// Confidence from the number of frames found, approaching 1 with more
+/// frames.
+fn frames_weight(nframes: usize) -> f64 {
+    1.0 - 1.0 / nframes as f64
+}
+
+/// Walk through count lines, assuming `extra` lines between count line and
+/// counted lines.
+fn infer_count_header(lines: &[&str], extra: usize) -> Option<FrameInference> {
+    let mut k = 0;
+    let mut nframes = 0;
+    while k < lines.len() {
+        let count: usize = lines[k].trim().parse().ok()?;
+        if count == 0 {
+            return None;
+        }
+        nframes += 1;
+        k += count + extra + 1;
+    }
+    // the last frame may be truncated in sample
+    if nframes < 2 {
+        return None;
+    }
+    let inference = FrameInference {
+        layout: FrameLayout::CountHeader { extra },
+        confidence: frames_weight(nframes),
+        nframes,
+    };
+    Some(inference)
+}
+
+fn infer_blank_separated(lines: &[&str]) -> Option<FrameInference> {
+    let blank = |i: usize| lines[i].trim().is_empty();
+    // frame starts with a non-blank line following blank lines
+    let starts: Vec<_> = (0..lines.len()).filter(|&i| !blank(i) && (i == 0 || blank(i - 1))).collect();
+    let nframes = starts.len();
+    if nframes < 2 {
+        return None;
+    }
+    let inference = FrameInference {
+        layout: FrameLayout::BlankSeparated,
+        confidence: frames_weight(nframes) * regularity(&starts),
+        nframes,
+    };
+    Some(inference)
+}
+
+/// Placeholder for numbers in line shape.
+const NUM: char = '\u{1}';
+
+/// Replace numbers in `line` with a placeholder as the line shape.
+fn line_shape(line: &str) -> String {
+    let mut shape = String::new();
+    let mut in_number = false;
+    for c in line.trim_end().chars() {
+        if c.is_ascii_digit() || (in_number && ".eEdD+-".contains(c)) {
+            if !in_number {
+                shape.push(NUM);
+            }
+            in_number = true;
+        } else {
+            shape.push(c);
+            in_number = false;
+        }
+    }
+    shape
+}
+
+/// Convert line shape into a regex pattern. A literal `|` is written as
+/// `\x7C`, since `GrepReader::mark` splits pattern at `|` into alternatives.
+fn shape_pattern(shape: &str) -> String {
+    let parts: Vec<_> = shape.split(NUM).map(|s| regex::escape(s).replace(r"\|", r"\x7C")).collect();
+    format!("^{}", parts.join(r"[-+0-9.eEdD]+"))
+}
+
+/// Find the first recurring line in the same shape as frame header.
+fn infer_header(lines: &[&str]) -> Option<FrameInference> {
+    let mut found: HashMap<String, Vec<usize>> = HashMap::new();
+    for (i, line) in lines.iter().enumerate() {
+        let shape = line_shape(line);
+        // blank line or a line of numbers is unlikely a header
+        if shape.chars().all(|c| c == NUM || c.is_whitespace()) {
+            continue;
+        }
+        found.entry(shape).or_default().push(i);
+    }
+    // header lines are rare, and should be found earliest
+    let max_count = (lines.len() / 3).max(2);
+    let (shape, starts) = found
+        .into_iter()
+        .filter(|(_, starts)| starts.len() >= 2 && starts.len() <= max_count)
+        .min_by_key(|(_, starts)| starts[0])?;
+    let nframes = starts.len();
+    let inference = FrameInference {
+        layout: FrameLayout::Header(shape_pattern(&shape)),
+        confidence: frames_weight(nframes) * regularity(&starts),
+        nframes,
+    };
+    Some(inference)
+}
+
+/// Find the repeating record structure in sampled `text`. Return all
+/// candidates sorted by confidence in descending order.
+pub fn infer_frame_layouts(text: &str) -> Vec<FrameInference> {
+    let lines: Vec<_> = text.lines().take(SAMPLE_LINES).collect();
+    let mut found: Vec<_> = (0..4).filter_map(|extra| infer_count_header(&lines, extra)).collect();
+    found.extend(infer_blank_separated(&lines));
+    found.extend(infer_header(&lines));
+    found.sort_by(|a, b| b.confidence.total_cmp(&a.confidence));
+    found
+}
+
+/// Find the most probable frame layout in sampled `text`.
+pub fn infer_frame_layout(text: &str) -> Option<FrameInference> {
+    infer_frame_layouts(text).into_iter().next()
+}
+// 7a31f4c8 ends here
+
+// [[file:../parser.note::e0b2f96d][e0b2f96d]]
+impl TextViewer {
+    /// Find the most probable frame layout in the beginning of the text.
+    pub fn infer_frames(&self) -> Option<FrameInference> {
+        infer_frame_layout(self.text())
+    }
+}
+
+impl<R: BufRead + Seek> TextReader<R> {
+    /// Find the most probable frame layout by sampling lines from current
+    /// position. The cursor will not be moved.
+    pub fn infer_frames(&mut self) -> Result<Option<FrameInference>> {
+        let pos = self.get_current_position()?;
+        let mut sample = String::new();
+        for _ in 0..SAMPLE_LINES {
+            if self.read_line(&mut sample)? == 0 {
+                break;
+            }
+        }
+        self.goto(pos)?;
+        Ok(infer_frame_layout(&sample))
+    }
+}
+// e0b2f96d ends here
+
+// [[file:../parser.note::9d6f1b20][9d6f1b20]]
+#[test]
+fn test_infer_frames() -> Result<()> {
+    use crate::GrepReader;
+
+    // count line plus a comment line
+    let f = "./tests/files/multi.xyz";
+    let view = TextViewer::try_from_path(f.as_ref())?;
+    let found = view.infer_frames().unwrap();
+    assert_eq!(found.layout, FrameLayout::CountHeader { extra: 1 });
+    assert_eq!(found.nframes, 6);
+
+    // blank line separated
+    let f = "./tests/files/multi.pxyz";
+    let mut reader = TextReader::try_from_path(f.as_ref())?;
+    let found = reader.infer_frames()?.unwrap();
+    assert_eq!(found.layout, FrameLayout::BlankSeparated);
+    let parts: Vec<_> = reader.partitions(found.layout.partitioner()?).collect();
+    assert_eq!(parts.len(), 6);
+    assert_eq!(parts[0].lines().count(), 17);
+
+    // recurring header line
+    let f = "./tests/files/lammps-test.dump";
+    let view = TextViewer::try_from_path(f.as_ref())?;
+    let found = view.infer_frames().unwrap();
+    assert_eq!(found.layout, FrameLayout::Header("^ITEM: TIMESTEP".into()));
+    assert!(found.confidence > 0.5);
+    let mut reader = GrepReader::try_from_path(f.as_ref())?;
+    assert_eq!(reader.mark(found.layout.grep_pattern().unwrap(), None)?, 3);
+
+    let found = infer_frame_layout("# Timestep 0\n1 2\n3 4\n# Timestep 10\n5 6\n7 8\n").unwrap();
+    assert_eq!(found.layout.grep_pattern(), Some(r"^\# Timestep [-+0-9.eEdD]+"));
+    let reader = TextReader::from_str("# Timestep 0\n1 2\n3 4\n# Timestep 10\n5 6\n7 8\n");
+    assert_eq!(reader.partitions(found.layout.partitioner()?).count(), 2);
+
+    // header line containing `|`
+    let txt = "| Step | 0\n1 2\n3 4\n| Step | 10\n5 6\n7 8\n";
+    let found = infer_frame_layout(txt).unwrap();
+    let pattern = found.layout.grep_pattern().unwrap();
+    assert!(!pattern.contains('|'));
+    assert_eq!(TextReader::from_str(txt).partitions(found.layout.partitioner()?).count(), 2);
+    let dir = gut::fs::tempfile::tempdir()?;
+    let f = dir.path().join("bar.txt");
+    std::fs::write(&f, txt)?;
+    assert_eq!(GrepReader::try_from_path(&f)?.mark(pattern, None)?, 2);
+
+    // nothing repeating
+    assert!(infer_frame_layout("a\nb\nc\n").is_none());
+
+    Ok(())
+}
+// 9d6f1b20 ends here
@@ -23,7 +23,7 @@ use std::path::Path;
 // cbed1309 ends here
 
 // [[file:../parser.note::9b3ecbac][9b3ecbac]]
-// mod grep;
+mod grep;
 mod reader;
 mod view;
 
@@ -34,9 +34,16 @@ mod common {
 
 // [[file:../parser.note::ff35c905][ff35c905]]
 pub mod parsers;
+pub mod partition;
+pub mod split;
+pub mod infer;
+pub mod multi;
+pub mod batch;
+pub mod progress;
+pub mod limits;
+pub mod newline;
 pub use crate::reader::*;
 
 pub use crate::view::TextViewer;
-pub use grep_reader::GrepReader;
-// pub use crate::grep::GrepReader;
+pub use crate::grep::{GrepReader, MarkerCursor, MarkerIndex, RegionMarkers};
 // ff35c905 ends here
@@ -0,0 +1,213 @@
+// [[file:../parser.note::*docs][docs:1]]
+//! Resource limits and binary content detection for reading untrusted
+//! input.
+//!
+//! # Example
+//!
+//! ```
+//! use gchemol_parser::limits::{LimitError, Limits};
+//! use gchemol_parser::TextReader;
+//!
+//! let limits = Limits::new().max_line_length(8).detect_binary(true);
+//! let mut reader = TextReader::from_str("short\na very long line\n").with_limits(limits);
+//! let mut line = String::new();
+//! assert!(reader.read_line(&mut line).is_ok());
+//! let e = reader.read_line(&mut line).unwrap_err();
+//! assert_eq!(e.downcast_ref::<LimitError>(), Some(&LimitError::LineTooLong { limit: 8 }));
+//! ```
+// docs:1 ends here
+
+// [[file:../parser.note::5f8a1d3e][5f8a1d3e]]
+/// Limits on resources used in reading text. No limit is set by default.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Limits {
+    max_line_length: Option<usize>,
+    max_part_size: Option<usize>,
+    max_buffered: Option<usize>,
+    detect_binary: bool,
+}
+
+impl Limits {
+    /// Create limits without any limit set.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Fail if a line is longer than `n` bytes, including line ending.
+    pub fn max_line_length(mut self, n: usize) -> Self {
+        self.max_line_length = Some(n);
+        self
+    }
+
+    /// Fail if a part from `Partitions` is larger than `n` bytes.
+    pub fn max_part_size(mut self, n: usize) -> Self {
+        self.max_part_size = Some(n);
+        self
+    }
+
+    /// Fail if more than `n` bytes are buffered in memory, such as lines
+    /// buffered in `Partitions`, or text read in `read_to_string`.
+    pub fn max_buffered(mut self, n: usize) -> Self {
+        self.max_buffered = Some(n);
+        self
+    }
+
+    /// Fail if NUL byte is found in text, which indicates binary content.
+    pub fn detect_binary(mut self, detect: bool) -> Self {
+        self.detect_binary = detect;
+        self
+    }
+
+    /// Return true if no limit is set.
+    pub fn is_unlimited(&self) -> bool {
+        *self == Self::default()
+    }
+
+    /// Check a line of `n` bytes in length.
+    pub(crate) fn check_line_length(&self, n: usize) -> Result<(), LimitError> {
+        match self.max_line_length {
+            Some(limit) if n > limit => Err(LimitError::LineTooLong { limit }),
+            _ => Ok(()),
+        }
+    }
+
+    /// Check a part of `n` bytes in size.
+    pub(crate) fn check_part_size(&self, n: usize) -> Result<(), LimitError> {
+        match self.max_part_size {
+            Some(limit) if n > limit => Err(LimitError::PartTooLarge { limit }),
+            _ => Ok(()),
+        }
+    }
+
+    /// Check `n` bytes buffered.
+    pub(crate) fn check_buffered(&self, n: usize) -> Result<(), LimitError> {
+        match self.max_buffered {
+            Some(limit) if n > limit => Err(LimitError::BufferFull { limit }),
+            _ => Ok(()),
+        }
+    }
+
+    /// Check binary content in `bytes`.
+    pub(crate) fn check_binary(&self, bytes: &[u8]) -> Result<(), LimitError> {
+        if self.detect_binary && bytes.contains(&0) {
+            Err(LimitError::BinaryContent)
+        } else {
+            Ok(())
+        }
+    }
+
+    /// Check all limits on the whole text.
+    pub(crate) fn check_text(&self, text: &str) -> Result<(), LimitError> {
+        self.check_buffered(text.len())?;
+        self.check_binary(text.as_bytes())?;
+        if self.max_line_length.is_some() {
+            for line in text.split_inclusive('\n') {
+                self.check_line_length(line.len())?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// The error when a limit in [`Limits`] is exceeded.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LimitError {
+    /// A line longer than the limit in bytes.
+    LineTooLong {
+        /// The maximum line length.
+        limit: usize,
+    },
+    /// A part larger than the limit in bytes.
+    PartTooLarge {
+        /// The maximum part size.
+        limit: usize,
+    },
+    /// Buffered bytes exceed the limit.
+    BufferFull {
+        /// The maximum number of bytes buffered.
+        limit: usize,
+    },
+    /// NUL byte found in text.
+    BinaryContent,
+}
+
+impl std::fmt::Display for LimitError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            Self::LineTooLong { limit } => write!(f, "line longer than {limit} bytes"),
+            Self::PartTooLarge { limit } => write!(f, "part larger than {limit} bytes"),
+            Self::BufferFull { limit } => write!(f, "more than {limit} bytes buffered"),
+            Self::BinaryContent => write!(f, "binary content found"),
+        }
+    }
+}
+
+impl std::error::Error for LimitError {}
+// 5f8a1d3e ends here
+
+// [[file:../parser.note::2e7c0a94][2e7c0a94]]
+#[test]
+fn test_limits() -> gut::prelude::Result<()> {
+    use crate::partition::{FixedLines, Preceded};
+    use crate::{TextReader, TextViewer};
+
+    // a long line without line ending
+    let txt = "a".repeat(10000);
+    let mut reader = TextReader::from_str(&txt).with_limits(Limits::new().max_line_length(100));
+    let mut s = String::new();
+    let e = reader.read_line(&mut s).unwrap_err();
+    assert_eq!(e.downcast_ref(), Some(&LimitError::LineTooLong { limit: 100 }));
+    assert!(s.is_empty());
+    let mut reader = TextReader::from_str(&txt).with_limits(Limits::new().max_buffered(1000));
+    let e = reader.read_to_string(&mut s).unwrap_err();
+    assert_eq!(e.downcast_ref(), Some(&LimitError::BufferFull { limit: 1000 }));
+
+    // limits in partitions
+    let txt = "head\n".to_owned() + &"line\n".repeat(100) + "head\nline\n";
+    let p = Preceded(|line: &str| line.starts_with("head"));
+    let reader = TextReader::from_str(&txt).with_limits(Limits::new().max_part_size(100));
+    let mut parts = reader.partitions(p);
+    assert!(parts.next().is_none());
+    let e = parts.error().unwrap();
+    assert_eq!(e.limit_error(), Some(&LimitError::PartTooLarge { limit: 100 }));
+    let p = Preceded(|line: &str| line.starts_with("head"));
+    let reader = TextReader::from_str(&txt).with_limits(Limits::new().max_buffered(100));
+    let mut parts = reader.partitions(p);
+    assert!(parts.next().is_none());
+    let e = parts.error().unwrap();
+    assert_eq!(e.limit_error(), Some(&LimitError::BufferFull { limit: 100 }));
+    assert_eq!(e.line, 21);
+    let reader = TextReader::from_str(&txt).with_limits(Limits::new().max_part_size(100).max_line_length(10));
+    assert_eq!(reader.partitions(FixedLines(10)).count(), 11);
+
+    // binary content
+    let dir = gut::fs::tempfile::tempdir()?;
+    let f = dir.path().join("binary.dat");
+    std::fs::write(&f, b"text\n\x00\x01\x02\n")?;
+    let limits = Limits::new().detect_binary(true);
+    let reader = TextReader::try_from_path(&f)?.with_limits(limits);
+    let mut parts = reader.partitions(FixedLines(1));
+    assert_eq!(parts.next().as_deref(), Some("text\n"));
+    assert!(parts.next().is_none());
+    assert_eq!(parts.error().unwrap().limit_error(), Some(&LimitError::BinaryContent));
+    let e = TextViewer::try_from_path_with_limits(&f, &limits).unwrap_err();
+    assert_eq!(e.downcast_ref(), Some(&LimitError::BinaryContent));
+    // checked before decoding
+    std::fs::write(&f, b"text\n\x00\xff\xfe\n")?;
+    let e = TextViewer::try_from_path_with_limits(&f, &limits).unwrap_err();
+    assert_eq!(e.downcast_ref(), Some(&LimitError::BinaryContent));
+
+    // viewer
+    let f = "./tests/files/multi.xyz";
+    let e = TextViewer::try_from_path_with_limits(f.as_ref(), &Limits::new().max_buffered(1000)).unwrap_err();
+    assert_eq!(e.downcast_ref(), Some(&LimitError::BufferFull { limit: 1000 }));
+    let e = TextViewer::try_from_path_with_limits(f.as_ref(), &Limits::new().max_line_length(30)).unwrap_err();
+    assert_eq!(e.downcast_ref(), Some(&LimitError::LineTooLong { limit: 30 }));
+    let view = TextViewer::try_from_path_with_limits(f.as_ref(), &Limits::new().max_line_length(100).detect_binary(true))?;
+    assert_eq!(view.num_lines(), 100);
+    // no limit by default
+    assert_eq!(TextViewer::try_from_path(f.as_ref())?.text(), view.text());
+
+    Ok(())
+}
+// 2e7c0a94 ends here
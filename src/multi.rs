@@ -0,0 +1,426 @@
+// [[file:../parser.note::*docs][docs:1]]
+//! Read an ordered list of files as one logical text stream, such as
+//! trajectory files split across restarted runs.
+//!
+//! # Example
+//!
+//! ```
+//! use gchemol_parser::multi::MultiFile;
+//! use gchemol_parser::partition::Preceded;
+//!
+//! let f = "./tests/files/lammps-test.dump";
+//! let files = MultiFile::try_from_paths(&[f, f]).unwrap();
+//! let reader = files.text_reader().unwrap();
+//! let p = Preceded(|line: &str| line.starts_with("ITEM: TIMESTEP"));
+//! let parts: Vec<_> = reader.partitions(p).with_metadata().collect();
+//! assert_eq!(parts.len(), 6);
+//! // the fourth frame is the first frame in the second file
+//! assert_eq!(files.locate(parts[3].start), Some((1, 0)));
+//! assert_eq!(files.locate_line(parts[3].first_line).unwrap(), Some((1, 1)));
+//! ```
+// docs:1 ends here
+
+// [[file:../parser.note::6c2e9a4f][6c2e9a4f]]
+use crate::grep::grep_lib::mark_matched_positions_with_ripgrep;
+use crate::newline::Newline;
+use crate::reader::TextReader;
+
+use gut::fs::*;
+use gut::prelude::*;
+
+use std::cell::OnceCell;
+use std::collections::HashSet;
+use std::hash::Hash;
+use std::io::SeekFrom;
+use std::ops::Range;
+// 6c2e9a4f ends here
+
+// [[file:../parser.note::b1f7d358][b1f7d358]]
+#[derive(Debug, Clone)]
+struct FileEntry {
+    path: PathBuf,
+    // the number of bytes in file
+    len: u64,
+    // the position of file start in the concatenated stream
+    start: u64,
+    // a line ending is appended if missing at the end of file
+    padded: bool,
+}
+
+impl FileEntry {
+    /// The number of bytes in the concatenated stream.
+    fn stream_len(&self) -> u64 {
+        self.len + self.padded as u64
+    }
+}
+
+/// Locate the file in `entries` containing `position` in the concatenated
+/// stream. Return the file index and the position in the file.
+fn locate_entry(entries: &[FileEntry], position: u64) -> Option<(usize, u64)> {
+    let i = entries.partition_point(|e| e.start + e.stream_len() <= position);
+    let entry = entries.get(i)?;
+    Some((i, position - entry.start))
+}
+
+/// An ordered list of files read as one logical text stream. A line ending
+/// is inserted after a file not ending with one, so that lines from
+/// different files are never joined.
+#[derive(Debug)]
+pub struct MultiFile {
+    entries: Vec<FileEntry>,
+    // the number of lines in each file, counted on demand
+    line_counts: OnceCell<Vec<usize>>,
+}
+
+/// A marked position in [`MultiFile`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FileMarker {
+    /// The index of the file in the list.
+    pub file: usize,
+    /// The position in the file.
+    pub position: u64,
+    /// The position in the concatenated stream.
+    pub global_position: u64,
+}
+
+/// A frame in one file of [`MultiFile`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FileFrame {
+    /// The index of the file in the list.
+    pub file: usize,
+    /// The byte range in the file.
+    pub span: Range<u64>,
+    /// The byte range in the concatenated stream.
+    pub global_span: Range<u64>,
+}
+
+impl MultiFile {
+    /// Concatenate files in `paths` in order.
+    pub fn try_from_paths<P: AsRef<Path>>(paths: &[P]) -> Result<Self> {
+        let mut entries = vec![];
+        let mut start = 0;
+        for p in paths {
+            let path = p.as_ref();
+            let mut f = File::open(path).with_context(|| format!("Failed to open file {:?}", path))?;
+            let len = f.metadata()?.len();
+            let padded = if len > 0 {
+                let mut last = [0u8];
+                f.seek(SeekFrom::End(-1))?;
+                f.read_exact(&mut last)?;
+                last[0] != b'\n'
+            } else {
+                false
+            };
+            let entry = FileEntry {
+                path: path.to_owned(),
+                len,
+                start,
+                padded,
+            };
+            start += entry.stream_len();
+            entries.push(entry);
+        }
+        let files = Self {
+            entries,
+            line_counts: OnceCell::new(),
+        };
+        Ok(files)
+    }
+
+    /// Return the number of files.
+    pub fn num_files(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// Return the path of file `i`.
+    pub fn path(&self, i: usize) -> Option<&Path> {
+        self.entries.get(i).map(|e| e.path.as_path())
+    }
+
+    /// Return the total number of bytes in the concatenated stream.
+    pub fn total_len(&self) -> u64 {
+        self.entries.last().map(|e| e.start + e.stream_len()).unwrap_or(0)
+    }
+
+    /// Return a text reader over the concatenated stream.
+    pub fn text_reader(&self) -> Result<TextReader<BufReader<MultiFileReader>>> {
+        let reader = MultiFileReader {
+            entries: self.entries.clone(),
+            pos: 0,
+            current: None,
+        };
+        Ok(TextReader::new(reader))
+    }
+}
+// b1f7d358 ends here
+
+// [[file:../parser.note::4f0a6d92][4f0a6d92]]
+impl MultiFile {
+    /// Locate the file containing `position` in the concatenated stream.
+    /// Return the file index and the position in the file.
+    pub fn locate(&self, position: u64) -> Option<(usize, u64)> {
+        locate_entry(&self.entries, position)
+    }
+
+    /// Return the position in the concatenated stream for `position` in
+    /// file `i`.
+    pub fn global_position(&self, i: usize, position: u64) -> Option<u64> {
+        self.entries.get(i).map(|e| e.start + position)
+    }
+
+    fn line_counts(&self) -> Result<&[usize]> {
+        if self.line_counts.get().is_none() {
+            let mut counts = vec![];
+            for entry in self.entries.iter() {
+                let mut reader = BufReader::new(File::open(&entry.path)?);
+                let mut n = entry.padded as usize;
+                loop {
+                    let buf = reader.fill_buf()?;
+                    if buf.is_empty() {
+                        break;
+                    }
+                    n += buf.iter().filter(|&&b| b == b'\n').count();
+                    let m = buf.len();
+                    reader.consume(m);
+                }
+                counts.push(n);
+            }
+            let _ = self.line_counts.set(counts);
+        }
+        Ok(self.line_counts.get().unwrap())
+    }
+
+    /// Locate the file containing line `line` (1-based) in the concatenated
+    /// stream. Return the file index and the line number in the file.
+    pub fn locate_line(&self, line: usize) -> Result<Option<(usize, usize)>> {
+        ensure!(line > 0, "line number starts from 1");
+        let mut n = line;
+        for (i, &m) in self.line_counts()?.iter().enumerate() {
+            if n <= m {
+                return Ok(Some((i, n)));
+            }
+            n -= m;
+        }
+        Ok(None)
+    }
+
+    /// Return the line number in the concatenated stream for line `line` in
+    /// file `i`.
+    pub fn global_line(&self, i: usize, line: usize) -> Result<usize> {
+        let counts = self.line_counts()?;
+        ensure!(i < counts.len(), "invalid file index {i}");
+        Ok(counts[..i].iter().sum::<usize>() + line)
+    }
+}
+// 4f0a6d92 ends here
+
+// [[file:../parser.note::8d3e5b70][8d3e5b70]]
+impl MultiFile {
+    /// Mark positions that matching `pattern` in all files, reported per
+    /// file and globally.
+    pub fn mark(&self, pattern: &str) -> Result<Vec<FileMarker>> {
+        let mut markers = vec![];
+        for (i, entry) in self.entries.iter().enumerate() {
+            let found = mark_matched_positions_with_ripgrep(pattern, &entry.path, None, Newline::default())?;
+            markers.extend(found.into_iter().map(|position| FileMarker {
+                file: i,
+                position,
+                global_position: entry.start + position,
+            }));
+        }
+        Ok(markers)
+    }
+
+    /// Return frames starting at lines matching `pattern`. A frame ends at
+    /// the next marker in the same file, or at the end of file. Text before
+    /// the first marker in each file is ignored.
+    pub fn frames(&self, pattern: &str) -> Result<Vec<FileFrame>> {
+        let markers = self.mark(pattern)?;
+        let frames = markers
+            .iter()
+            .enumerate()
+            .map(|(k, m)| {
+                let entry = &self.entries[m.file];
+                let end = match markers.get(k + 1) {
+                    Some(next) if next.file == m.file => next.position,
+                    _ => entry.len,
+                };
+                FileFrame {
+                    file: m.file,
+                    span: m.position..end,
+                    global_span: entry.start + m.position..entry.start + end,
+                }
+            })
+            .collect();
+        Ok(frames)
+    }
+
+    /// Read text of `frame` into `buf`. Return the number of bytes read.
+    pub fn read_frame(&self, frame: &FileFrame, buf: &mut String) -> Result<usize> {
+        let entry = self.entries.get(frame.file).ok_or_else(|| anyhow!("invalid file index {}", frame.file))?;
+        let mut f = File::open(&entry.path)?;
+        read_span(&mut f, &frame.span, buf)
+    }
+
+    /// Drop frames with the same key returned from `key`, keeping the last
+    /// one, so that frames in later files take priority, as duplicated in
+    /// restarted runs. Frames without a key are always kept.
+    pub fn dedup_frames<K, F>(&self, frames: Vec<FileFrame>, mut key: F) -> Result<Vec<FileFrame>>
+    where
+        K: Hash + Eq,
+        F: FnMut(&str) -> Option<K>,
+    {
+        let mut seen = HashSet::new();
+        let mut kept = vec![];
+        let mut opened: Option<(usize, File)> = None;
+        let mut buf = String::new();
+        for frame in frames.into_iter().rev() {
+            let f = match opened {
+                Some((i, ref mut f)) if i == frame.file => f,
+                _ => {
+                    let entry = self.entries.get(frame.file).ok_or_else(|| anyhow!("invalid file index {}", frame.file))?;
+                    &mut opened.insert((frame.file, File::open(&entry.path)?)).1
+                }
+            };
+            buf.clear();
+            read_span(f, &frame.span, &mut buf)?;
+            let unique = key(&buf).is_none_or(|k| seen.insert(k));
+            if unique {
+                kept.push(frame);
+            } else {
+                debug!("drop duplicated frame in {:?}", self.entries[frame.file].path);
+            }
+        }
+        kept.reverse();
+        Ok(kept)
+    }
+}
+
+fn read_span(f: &mut File, span: &Range<u64>, buf: &mut String) -> Result<usize> {
+    f.seek(SeekFrom::Start(span.start))?;
+    let n = Read::by_ref(f).take(span.end - span.start).read_to_string(buf)?;
+    Ok(n)
+}
+// 8d3e5b70 ends here
+
+// [[file:../parser.note::e58b1c06][e58b1c06]]
+/// A reader over the concatenated stream of [`MultiFile`].
+#[derive(Debug)]
+pub struct MultiFileReader {
+    entries: Vec<FileEntry>,
+    pos: u64,
+    // the index and handle of opened file
+    current: Option<(usize, File)>,
+}
+
+impl Read for MultiFileReader {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        let Some((i, local)) = locate_entry(&self.entries, self.pos) else {
+            return Ok(0);
+        };
+        if buf.is_empty() {
+            return Ok(0);
+        }
+        let entry = &self.entries[i];
+        // the line ending appended
+        if local >= entry.len {
+            buf[0] = b'\n';
+            self.pos += 1;
+            return Ok(1);
+        }
+        let f = match self.current {
+            Some((j, ref mut f)) if j == i => f,
+            _ => &mut self.current.insert((i, File::open(&entry.path)?)).1,
+        };
+        f.seek(SeekFrom::Start(local))?;
+        let m = buf.len().min((entry.len - local) as usize);
+        let n = f.read(&mut buf[..m])?;
+        self.pos += n as u64;
+        Ok(n)
+    }
+}
+
+impl Seek for MultiFileReader {
+    fn seek(&mut self, pos: SeekFrom) -> std::io::Result<u64> {
+        let total = self.entries.last().map(|e| e.start + e.stream_len()).unwrap_or(0);
+        let new = match pos {
+            SeekFrom::Start(p) => Some(p),
+            SeekFrom::End(d) => total.checked_add_signed(d),
+            SeekFrom::Current(d) => self.pos.checked_add_signed(d),
+        };
+        let new = new.ok_or_else(|| std::io::Error::new(std::io::ErrorKind::InvalidInput, "invalid seek position"))?;
+        self.pos = new;
+        Ok(new)
+    }
+}
+// e58b1c06 ends here
+
+// [[file:../parser.note::3a9f61c4][3a9f61c4]]
+#[test]
+fn test_multi_file() -> Result<()> {
+    use crate::partition::Preceded;
+
+    // restarted from timestep 100, and the first run was killed in the
+    // middle of writing a line ending
+    let txt = read_file("./tests/files/lammps-test.dump")?;
+    let lines: Vec<_> = txt.lines().collect();
+    let dir = tempfile::tempdir()?;
+    let f1 = dir.path().join("dump.1");
+    let f2 = dir.path().join("dump.2");
+    write_to_file(&f1, &lines[..546 * 2].join("\n"))?;
+    write_to_file(&f2, &(lines[546..].join("\n") + "\n"))?;
+
+    let files = MultiFile::try_from_paths(&[&f1, &f2])?;
+    assert_eq!(files.num_files(), 2);
+    assert_eq!(files.path(1), Some(f2.as_path()));
+    let n1 = files.entries[0].len;
+    assert_eq!(files.total_len(), n1 + 1 + files.entries[1].len);
+
+    // one logical stream
+    let mut reader = files.text_reader()?;
+    let mut s = String::new();
+    reader.read_to_string(&mut s)?;
+    assert_eq!(s.lines().count(), 546 * 4);
+    assert_eq!(s.len() as u64, files.total_len());
+    let p = Preceded(|line: &str| line.starts_with("ITEM: TIMESTEP"));
+    let parts: Vec<_> = files.text_reader()?.partitions(p).with_metadata().collect();
+    assert_eq!(parts.len(), 4);
+    assert_eq!(files.locate(parts[2].start), Some((1, 0)));
+    assert_eq!(files.locate(parts[1].end - 1), Some((0, n1)));
+    assert_eq!(files.locate(files.total_len()), None);
+    assert_eq!(files.locate_line(parts[3].first_line)?, Some((1, 547)));
+    assert_eq!(files.global_line(1, 547)?, parts[3].first_line);
+    assert_eq!(files.locate_line(546 * 4 + 1)?, None);
+
+    // seeking across files
+    reader.goto(parts[2].start - 1)?;
+    s.clear();
+    reader.read_line(&mut s)?;
+    assert_eq!(s, "\n");
+    s.clear();
+    reader.read_line(&mut s)?;
+    assert_eq!(s, "ITEM: TIMESTEP\n");
+
+    // markers per file and globally
+    let markers = files.mark("^ITEM: TIMESTEP")?;
+    assert_eq!(markers.len(), 4);
+    assert_eq!((markers[2].file, markers[2].position), (1, 0));
+    assert_eq!(markers[3].global_position, parts[3].start);
+    assert_eq!(files.global_position(1, 0), Some(parts[2].start));
+
+    // drop duplicated timestep 100 in the first file
+    let frames = files.frames("^ITEM: TIMESTEP")?;
+    assert_eq!(frames.len(), 4);
+    assert_eq!(frames[1].span.end, n1);
+    let timestep = |frame: &str| frame.lines().nth(1)?.trim().parse::<usize>().ok();
+    let frames = files.dedup_frames(frames, timestep)?;
+    assert_eq!(frames.len(), 3);
+    assert_eq!(frames.iter().map(|f| f.file).collect_vec(), [0, 1, 1]);
+    let mut s = String::new();
+    files.read_frame(&frames[1], &mut s)?;
+    assert_eq!(timestep(&s), Some(100));
+    assert_eq!(frames[1].global_span, parts[2].start..parts[2].end);
+
+    Ok(())
+}
+// 3a9f61c4 ends here
@@ -0,0 +1,208 @@
+// [[file:../parser.note::*docs][docs:1]]
+//! Newline policy for line endings in text.
+//!
+//! # Example
+//!
+//! ```
+//! use gchemol_parser::newline::Newline;
+//! use gchemol_parser::TextReader;
+//!
+//! let txt = "line 1\r\nline 2\rline 3\n";
+//! let mut reader = TextReader::from_str(txt).with_newline(Newline::Universal);
+//! let mut line = String::new();
+//! reader.read_line(&mut line).unwrap();
+//! reader.read_line(&mut line).unwrap();
+//! assert_eq!(line, "line 1\nline 2\n");
+//! ```
+// docs:1 ends here
+
+// [[file:../parser.note::7c41e9a6][7c41e9a6]]
+use gut::prelude::*;
+use std::borrow::Cow;
+use std::io::BufRead;
+
+/// How line endings are handled in reading text.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Newline {
+    /// Lines end with LF, and line endings are kept as they are, including
+    /// the CR in CRLF.
+    #[default]
+    Preserve,
+    /// Lines end with LF, and CRLF is converted to LF.
+    Normalize,
+    /// Lines end with LF, CRLF, or a single CR as in old Mac files, which are
+    /// all converted to LF.
+    Universal,
+}
+
+impl Newline {
+    /// Return the line endings in `s` converted according to this policy.
+    pub fn normalize<'a>(&self, s: &'a str) -> Cow<'a, str> {
+        match self {
+            Self::Preserve => s.into(),
+            Self::Normalize if s.contains("\r\n") => s.replace("\r\n", "\n").into(),
+            Self::Universal if s.contains('\r') => s.replace("\r\n", "\n").replace('\r', "\n").into(),
+            _ => s.into(),
+        }
+    }
+
+    /// Convert line endings in `s` from byte offset `start` in place.
+    pub(crate) fn normalize_from(&self, s: &mut String, start: usize) {
+        if let Cow::Owned(t) = self.normalize(&s[start..]) {
+            s.truncate(start);
+            s.push_str(&t);
+        }
+    }
+
+    /// Return true if byte `b` ends a line.
+    pub(crate) fn is_eol(&self, b: u8) -> bool {
+        b == b'\n' || (*self == Self::Universal && b == b'\r')
+    }
+
+    /// Read a line from `r` into `buf` with line ending converted. Return the
+    /// number of bytes read before conversion.
+    pub(crate) fn read_line<R: BufRead + ?Sized>(&self, r: &mut R, buf: &mut String) -> Result<usize> {
+        let start = buf.len();
+        let n = if *self == Self::Universal {
+            let mut line = vec![];
+            let n = self.read_line_bytes(r, &mut line)?;
+            buf.push_str(std::str::from_utf8(&line).context("stream did not contain valid UTF-8")?);
+            n
+        } else {
+            r.read_line(buf).context("Read line failure")?
+        };
+        self.normalize_from(buf, start);
+        Ok(n)
+    }
+
+    /// Read bytes of a line from `r` into `line`, including line ending.
+    /// Return the number of bytes read.
+    pub(crate) fn read_line_bytes<R: BufRead + ?Sized>(&self, r: &mut R, line: &mut Vec<u8>) -> Result<usize> {
+        let n0 = line.len();
+        loop {
+            let available = r.fill_buf().context("Read line failure")?;
+            let (n, done) = match available.iter().position(|&b| self.is_eol(b)) {
+                Some(i) => (i + 1, true),
+                None => (available.len(), available.is_empty()),
+            };
+            line.extend_from_slice(&available[..n]);
+            r.consume(n);
+            if done {
+                break;
+            }
+        }
+        // a single CR may be followed by LF
+        if line.ends_with(b"\r") && r.fill_buf()?.first() == Some(&b'\n') {
+            r.consume(1);
+            line.push(b'\n');
+        }
+        Ok(line.len() - n0)
+    }
+}
+// 7c41e9a6 ends here
+
+// [[file:../parser.note::0d5be3a8][0d5be3a8]]
+#[test]
+fn test_newline() {
+    let s = "a\r\nb\rc\n";
+    assert_eq!(Newline::Preserve.normalize(s), s);
+    assert_eq!(Newline::Normalize.normalize(s), "a\nb\rc\n");
+    assert_eq!(Newline::Universal.normalize(s), "a\nb\nc\n");
+
+    let mut s = "a\r\nb\r\n".to_owned();
+    Newline::Normalize.normalize_from(&mut s, 2);
+    assert_eq!(s, "a\r\nb\n");
+
+    let mut r = std::io::Cursor::new("a\r\nb\rc");
+    let mut s = String::new();
+    assert_eq!(Newline::Universal.read_line(&mut r, &mut s).unwrap(), 3);
+    assert_eq!(Newline::Universal.read_line(&mut r, &mut s).unwrap(), 2);
+    assert_eq!(Newline::Universal.read_line(&mut r, &mut s).unwrap(), 1);
+    assert_eq!(Newline::Universal.read_line(&mut r, &mut s).unwrap(), 0);
+    assert_eq!(s, "a\nb\nc");
+}
+
+#[test]
+fn test_newline_policy() -> Result<()> {
+    use crate::limits::Limits;
+    use crate::partition::FixedLines;
+    use crate::{GrepReader, TextReader, TextViewer};
+
+    // mixed line endings
+    let txt = "2\r\nold mac\rline a\nline b\r\n";
+    let expected = "2\nold mac\nline a\nline b\n";
+
+    // the same result from all reading components
+    let newline = Newline::Universal;
+    let mut s = String::new();
+    TextReader::from_str(txt).with_newline(newline).read_to_string(&mut s)?;
+    assert_eq!(s, expected);
+    s.clear();
+    let limits = Limits::new().max_line_length(100);
+    TextReader::from_str(txt).with_newline(newline).with_limits(limits).read_to_string(&mut s)?;
+    assert_eq!(s, expected);
+    let lines: Vec<_> = TextReader::from_str(txt).with_newline(newline).lines().collect();
+    assert_eq!(lines, ["2", "old mac", "line a", "line b"]);
+    let parts: Vec<_> = TextReader::from_str(txt).with_newline(newline).partitions(FixedLines(2)).collect();
+    assert_eq!(parts, ["2\nold mac\n", "line a\nline b\n"]);
+    let view = TextViewer::from_str(txt).with_newline(newline);
+    assert_eq!(view.text(), expected);
+
+    let dir = gut::fs::tempfile::tempdir()?;
+    let f = dir.path().join("mixed.txt");
+    std::fs::write(&f, txt)?;
+    let mut reader = GrepReader::try_from_path(&f)?;
+    reader.newline(newline);
+    assert_eq!(reader.mark("^line", None)?, 2);
+    s.clear();
+    reader.read_lines(2, &mut s)?;
+    assert_eq!(s, "2\nold mac\n");
+    s.clear();
+    reader.read_until_next_marker(&mut s)?;
+    assert_eq!(s, "line a\n");
+    let frames: Vec<_> = reader.read_frames(.., 1)?.collect::<Result<_>>()?;
+    assert_eq!(frames, ["line a\n", "line b\n"]);
+    s.clear();
+    reader.index()?.read_frame(1, &mut s)?;
+    assert_eq!(s, "line b\n");
+
+    // marking in old Mac files with a single CR as line ending
+    let mac = "2\rold mac\rline a\rline b\r";
+    let f = dir.path().join("mac.txt");
+    std::fs::write(&f, mac)?;
+    let mut reader = GrepReader::try_from_path(&f)?;
+    reader.newline(newline);
+    assert_eq!(reader.mark(r"^line \w$", None)?, 2);
+    let frames: Vec<_> = reader.read_frames(.., 1)?.collect::<Result<_>>()?;
+    assert_eq!(frames, ["line a\n", "line b\n"]);
+    assert_eq!(reader.mark_parallel(r"^line \w$", None)?, 2);
+    let markers = reader.mark_in_region("^line", 2..mac.len() as u64, None)?;
+    assert_eq!(markers.positions(), [8, 15]);
+    // the CR at file end could be followed by LF, so line c is not marked
+    std::fs::write(&f, format!("{mac}line c\r"))?;
+    assert_eq!(reader.remark(r"^line \w$")?, 1);
+    let frames: Vec<_> = reader.read_frames(.., 1)?.collect::<Result<_>>()?;
+    assert_eq!(frames, ["line a\n", "line b\nline c\n"]);
+    std::fs::write(&f, format!("{mac}line c\rline d\r"))?;
+    assert_eq!(reader.remark(r"^line \w$")?, 1);
+    let frames: Vec<_> = reader.read_frames(.., 1)?.collect::<Result<_>>()?;
+    assert_eq!(frames, ["line a\n", "line b\n", "line c\nline d\n"]);
+    // no match of a single CR without the policy
+    reader.newline(Newline::Preserve);
+    assert_eq!(reader.mark("^line", None)?, 0);
+
+    // CRLF converted, but a single CR kept in line
+    let newline = Newline::Normalize;
+    let parts: Vec<_> = TextReader::from_str(txt).with_newline(newline).partitions(FixedLines(2)).collect();
+    assert_eq!(parts, ["2\nold mac\rline a\n", "line b\n"]);
+
+    // CRLF converted by default in partitions
+    let parts: Vec<_> = TextReader::from_str(txt).partitions(FixedLines(2)).collect();
+    assert_eq!(parts, ["2\nold mac\rline a\n", "line b\n"]);
+    let newline = Newline::Preserve;
+    let parts: Vec<_> = TextReader::from_str(txt).with_newline(newline).partitions(FixedLines(2)).collect();
+    assert_eq!(parts, ["2\r\nold mac\rline a\n", "line b\r\n"]);
+
+    Ok(())
+}
+// 0d5be3a8 ends here
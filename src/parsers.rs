@@ -30,7 +30,7 @@ pub fn parse_error(e: winnow::error::ParseError<&str, winnow::error::ContextErro
 }
 
 /// Anything except whitespace, this parser will not consume "\n" character
-pub fn not_space<'a>(input: &mut &'a str) -> PResult<&'a str> {
+pub fn not_space<'a>(input: &mut &'a str) -> ModalResult<&'a str> {
     winnow::token::take_till(1.., |c| " \t\r\n".contains(c))
         .context(label("not_space"))
         .parse_next(input)
@@ -38,24 +38,24 @@ pub fn not_space<'a>(input: &mut &'a str) -> PResult<&'a str> {
 
 /// Read a new line including eol (\n) or consume the rest if there is no eol
 /// char.
-pub fn read_line<'a>(s: &mut &'a str) -> PResult<&'a str> {
+pub fn read_line<'a>(s: &mut &'a str) -> ModalResult<&'a str> {
     use winnow::ascii::till_line_ending;
     use winnow::combinator::opt;
 
     // use winnow::combinator::recognize;
     // if there is no newline in `s`, take the whole str
-    let o = (till_line_ending, opt(line_ending)).recognize().parse_next(s)?;
+    let o = (till_line_ending, opt(line_ending)).take().parse_next(s)?;
     Ok(o)
 }
 
 /// Take the rest line. The line ending is not included.
-pub fn rest_line<'a>(input: &mut &'a str) -> PResult<&'a str> {
+pub fn rest_line<'a>(input: &mut &'a str) -> ModalResult<&'a str> {
     use winnow::ascii::till_line_ending;
     terminated(till_line_ending, line_ending).context(label("rest line")).parse_next(input)
 }
 
 /// Take and consuming to `literal`.
-pub fn jump_to<'a>(literal: &str) -> impl FnMut(&mut &str) -> PResult<()> + '_ {
+pub fn jump_to<'a>(literal: &str) -> impl FnMut(&mut &str) -> ModalResult<()> + '_ {
     use winnow::token::take_until;
     move |input: &mut &str| {
         let _: (&str, &str) = (take_until(0.., literal), literal).context(label("jump_to")).parse_next(input)?;
@@ -64,7 +64,7 @@ pub fn jump_to<'a>(literal: &str) -> impl FnMut(&mut &str) -> PResult<()> + '_ {
 }
 
 /// Take until found `literal`. The `literal` will not be consumed.
-pub fn jump_until<'a>(literal: &str) -> impl FnMut(&mut &str) -> PResult<()> + '_ {
+pub fn jump_until<'a>(literal: &str) -> impl FnMut(&mut &str) -> ModalResult<()> + '_ {
     use winnow::token::take_until;
     move |input: &mut &str| {
         let _: &str = take_until(0.., literal).context(label("jump_until")).parse_next(input)?;
@@ -84,30 +84,79 @@ where
 }
 // fb1326ab ends here
 
+// [[file:../parser.note::b2f04c97][b2f04c97]]
+use crate::newline::Newline;
+
+/// Match a line ending according to `newline` policy: LF or CRLF, and also a
+/// single CR for `Newline::Universal`.
+pub fn line_ending_with<'a>(newline: Newline) -> impl FnMut(&mut &'a str) -> ModalResult<&'a str> {
+    use winnow::combinator::alt;
+
+    move |input: &mut &'a str| match newline {
+        Newline::Universal => alt(("\r\n", "\n", "\r")).context(label("line ending")).parse_next(input),
+        _ => line_ending.context(label("line ending")).parse_next(input),
+    }
+}
+
+/// Take until a line ending according to `newline` policy.
+fn till_line_ending_with<'a>(newline: Newline) -> impl FnMut(&mut &'a str) -> ModalResult<&'a str> {
+    use winnow::ascii::till_line_ending;
+    use winnow::token::take_till;
+
+    move |input: &mut &'a str| match newline {
+        Newline::Universal => take_till(0.., ['\r', '\n']).parse_next(input),
+        _ => till_line_ending.parse_next(input),
+    }
+}
+
+/// Read a new line including line ending like [`read_line`], with line
+/// ending recognized according to `newline` policy. The line ending is
+/// returned as it is in input.
+pub fn read_line_with<'a>(newline: Newline) -> impl FnMut(&mut &'a str) -> ModalResult<&'a str> {
+    use winnow::combinator::opt;
+
+    move |input: &mut &'a str| {
+        (till_line_ending_with(newline), opt(line_ending_with(newline)))
+            .take()
+            .parse_next(input)
+    }
+}
+
+/// Take the rest line like [`rest_line`], with line ending recognized
+/// according to `newline` policy. The line ending is not included.
+pub fn rest_line_with<'a>(newline: Newline) -> impl FnMut(&mut &'a str) -> ModalResult<&'a str> {
+    move |input: &mut &'a str| {
+        terminated(till_line_ending_with(newline), line_ending_with(newline))
+            .context(label("rest line"))
+            .parse_next(input)
+    }
+}
+// b2f04c97 ends here
+
 // [[file:../parser.note::3d14b516][3d14b516]]
 /// Match one unsigned integer: 123
-pub fn unsigned_integer<'a>(input: &mut &'a str) -> PResult<usize> {
+pub fn unsigned_integer<'a>(input: &mut &'a str) -> ModalResult<usize> {
     digit1.try_map(|x: &str| x.parse()).context(label("usize")).parse_next(input)
 }
 
 /// Match one signed integer: -123 or +123
-pub fn signed_integer(s: &mut &str) -> PResult<isize> {
+pub fn signed_integer(s: &mut &str) -> ModalResult<isize> {
     use winnow::combinator::alt;
     use winnow::combinator::opt;
 
     let sign = opt(alt(("-", "+")));
-    (sign, digit1).recognize().try_map(|x: &str| x.parse::<isize>()).parse_next(s)
+    (sign, digit1).take().try_map(|x: &str| x.parse::<isize>()).parse_next(s)
 }
 
 /// Parse a line containing an unsigned integer number.
-pub fn read_usize(s: &mut &str) -> PResult<usize> {
+pub fn read_usize(s: &mut &str) -> ModalResult<usize> {
     // allow white spaces
     let p = delimited(space0, unsigned_integer, space0);
     terminated(p, line_ending).parse_next(s)
 }
 
 /// Parse a line containing many unsigned numbers
-pub fn read_usize_many(s: &mut &str) -> PResult<Vec<usize>> {
+pub fn read_usize_many(s: &mut &str) -> ModalResult<Vec<usize>> {
     let x = seq! {
         _: space0,
         separated(1.., unsigned_integer, space1),
@@ -124,26 +173,26 @@ pub use self::unsigned_integer as unsigned_digit;
 
 // [[file:../parser.note::4ef79da3][4ef79da3]]
 /// Parse a f64 float number
-pub fn double(input: &mut &str) -> PResult<f64> {
+pub fn double(input: &mut &str) -> ModalResult<f64> {
     use winnow::ascii::float;
     float(input)
 }
 
 /// Consume three float numbers separated by one or more spaces. Return xyz array.
-pub fn xyz_array(s: &mut &str) -> PResult<[f64; 3]> {
+pub fn xyz_array(s: &mut &str) -> ModalResult<[f64; 3]> {
     let x = seq! {double, _: space1, double, _: space1, double}.parse_next(s)?;
     Ok([x.0, x.1, x.2])
 }
 
 /// Parse a line containing a float number possibly surrounded by spaces
-pub fn read_double(s: &mut &str) -> PResult<f64> {
+pub fn read_double(s: &mut &str) -> ModalResult<f64> {
     // allow white spaces
     let p = delimited(space0, double, space0);
     terminated(p, line_ending).parse_next(s)
 }
 
 /// Parse a line containing many float numbers
-pub fn read_double_many(s: &mut &str) -> PResult<Vec<f64>> {
+pub fn read_double_many(s: &mut &str) -> ModalResult<Vec<f64>> {
     let x = seq! {
         _: space0,
         separated(1.., double, space1),
@@ -155,6 +204,221 @@ pub fn read_double_many(s: &mut &str) -> PResult<Vec<f64>> {
 }
 // 4ef79da3 ends here
 
+// [[file:../parser.note::8d2e5b7f][8d2e5b7f]]
+/// Parse a complex number in Fortran list-directed style, such as
+/// `(1.234E-01,-5.6E-02)`. Return `[re, im]`.
+pub fn complex(s: &mut &str) -> ModalResult<[f64; 2]> {
+    let x = seq! {
+        _: ("(", space0),
+        fortran_double,
+        _: (space0, ",", space0),
+        fortran_double,
+        _: (space0, ")"),
+    }
+    .context(label("complex"))
+    .parse_next(s)?;
+    Ok([x.0, x.1])
+}
+
+/// Float numbers separated by comma or whitespace.
+fn vector_items(s: &mut &str) -> ModalResult<Vec<f64>> {
+    use winnow::combinator::alt;
+
+    let sep = alt(((space0, ",", space0).void(), space1.void()));
+    delimited(space0, separated(0.., fortran_double, sep), space0).parse_next(s)
+}
+
+/// Parse a vector of any length in brackets or parentheses, with items
+/// separated by comma or whitespace, such as `[0.1, 0.2, 0.3]` or
+/// `( 1.0  2.0  3.0 )`.
+pub fn vector(s: &mut &str) -> ModalResult<Vec<f64>> {
+    use winnow::combinator::alt;
+
+    alt((delimited("[", vector_items, "]"), delimited("(", vector_items, ")")))
+        .context(label("vector"))
+        .parse_next(s)
+}
+
+/// Parse a 3x3 tensor printed in a single row of 9 numbers, or across three
+/// rows of 3 numbers. The line ending of each row is consumed, and the last
+/// row may end without line ending.
+pub fn tensor3x3(s: &mut &str) -> ModalResult<[[f64; 3]; 3]> {
+    tensor3x3_with(Newline::default()).parse_next(s)
+}
+
+/// Parse a 3x3 tensor like [`tensor3x3`], with line ending recognized
+/// according to `newline` policy.
+pub fn tensor3x3_with<'a>(newline: Newline) -> impl FnMut(&mut &'a str) -> ModalResult<[[f64; 3]; 3]> {
+    use winnow::combinator::alt;
+
+    move |s: &mut &'a str| {
+        let row3 = |s: &mut &'a str| {
+            let x = seq! {
+                _: space0,
+                fortran_double,
+                _: space1,
+                fortran_double,
+                _: space1,
+                fortran_double,
+                _: (space0, line_ending_or_eof_with(newline)),
+            }
+            .parse_next(s)?;
+            Ok([x.0, x.1, x.2])
+        };
+        let rows = (row3, row3, row3).map(|(a, b, c)| [a, b, c]);
+        let row9 = |s: &mut &'a str| {
+            let x: Vec<f64> = delimited(space0, separated(9, fortran_double, space1), (space0, line_ending_or_eof_with(newline))).parse_next(s)?;
+            Ok([[x[0], x[1], x[2]], [x[3], x[4], x[5]], [x[6], x[7], x[8]]])
+        };
+        alt((rows, row9)).context(label("tensor3x3")).parse_next(s)
+    }
+}
+
+#[test]
+fn test_complex_vector_tensor() {
+    let (rest, z) = complex.parse_peek("(1.234E-01,-5.6E-02) x").unwrap();
+    assert_eq!((rest, z), (" x", [1.234E-01, -5.6E-02]));
+    let (_, z) = complex.parse_peek("( 1.0D+00 , 2.0D+00 )").unwrap();
+    assert_eq!(z, [1.0, 2.0]);
+    assert!(complex.parse_peek("(1.0 2.0)").is_err());
+
+    let (rest, v) = vector.parse_peek("[0.1, 0.2, 0.3]\n").unwrap();
+    assert_eq!((rest, v), ("\n", vec![0.1, 0.2, 0.3]));
+    let (_, v) = vector.parse_peek("( 1.0  2.0  3.0 )").unwrap();
+    assert_eq!(v, [1.0, 2.0, 3.0]);
+    let (_, v) = vector.parse_peek("[1,2 ,3 ,  4 5]").unwrap();
+    assert_eq!(v, [1.0, 2.0, 3.0, 4.0, 5.0]);
+    let (_, v) = vector.parse_peek("[ ]").unwrap();
+    assert!(v.is_empty());
+    assert!(vector.parse_peek("[1.0, 2.0)").is_err());
+    assert!(vector.parse_peek("[1.0,,2.0]").is_err());
+
+    let expected = [[1.0, 2.0, 3.0], [4.0, 5.0, 6.0], [7.0, 8.0, 9.0]];
+    let (rest, t) = tensor3x3.parse_peek(" 1 2 3 4 5 6 7 8 9\nnext").unwrap();
+    assert_eq!((rest, t), ("next", expected));
+    let txt = "  1.0  2.0  3.0\n  4.0  5.0  6.0\n  7.0  8.0  9.0\nnext";
+    let (rest, t) = tensor3x3.parse_peek(txt).unwrap();
+    assert_eq!((rest, t), ("next", expected));
+    let (_, t) = tensor3x3.parse_peek("1 2 3\r\n4 5 6\r\n7 8 9").unwrap();
+    assert_eq!(t, expected);
+    assert!(tensor3x3.parse_peek("1 2 3\n4 5 6\n").is_err());
+    assert!(tensor3x3.parse_peek("1 2 3 4 5 6 7 8\n").is_err());
+    assert!(tensor3x3.parse_peek("1 2 3\r4 5 6\r7 8 9").is_err());
+    let (_, t) = tensor3x3_with(Newline::Universal).parse_peek("1 2 3\r4 5 6\r7 8 9").unwrap();
+    assert_eq!(t, expected);
+}
+// 8d2e5b7f ends here
+
+// [[file:../parser.note::6e1fa0c4][6e1fa0c4]]
+/// Match a line ending, or the end of input for the last line without line
+/// ending.
+pub fn line_ending_or_eof<'a>(input: &mut &'a str) -> ModalResult<&'a str> {
+    line_ending_or_eof_with(Newline::default()).parse_next(input)
+}
+
+/// Take the rest line like [`rest_line`], but also accept the last line
+/// without line ending. Fail on empty input.
+pub fn rest_line_or_eof<'a>(input: &mut &'a str) -> ModalResult<&'a str> {
+    rest_line_or_eof_with(Newline::default()).parse_next(input)
+}
+
+/// Parse a line containing an unsigned integer number like [`read_usize`],
+/// but also accept the last line without line ending.
+pub fn read_usize_or_eof(s: &mut &str) -> ModalResult<usize> {
+    read_usize_or_eof_with(Newline::default()).parse_next(s)
+}
+
+/// Parse a line containing many unsigned numbers like [`read_usize_many`],
+/// but also accept the last line without line ending.
+pub fn read_usize_many_or_eof(s: &mut &str) -> ModalResult<Vec<usize>> {
+    read_usize_many_or_eof_with(Newline::default()).parse_next(s)
+}
+
+/// Parse a line containing a float number like [`read_double`], but also
+/// accept the last line without line ending.
+pub fn read_double_or_eof(s: &mut &str) -> ModalResult<f64> {
+    read_double_or_eof_with(Newline::default()).parse_next(s)
+}
+
+/// Parse a line containing many float numbers like [`read_double_many`], but
+/// also accept the last line without line ending.
+pub fn read_double_many_or_eof(s: &mut &str) -> ModalResult<Vec<f64>> {
+    read_double_many_or_eof_with(Newline::default()).parse_next(s)
+}
+
+/// Match a line ending like [`line_ending_or_eof`], with line ending
+/// recognized according to `newline` policy.
+pub fn line_ending_or_eof_with<'a>(newline: Newline) -> impl FnMut(&mut &'a str) -> ModalResult<&'a str> {
+    use winnow::combinator::{alt, eof};
+
+    move |input: &mut &'a str| {
+        alt((line_ending_with(newline), eof))
+            .context(label("line ending or eof"))
+            .parse_next(input)
+    }
+}
+
+/// Take the rest line like [`rest_line_or_eof`], with line ending recognized
+/// according to `newline` policy.
+pub fn rest_line_or_eof_with<'a>(newline: Newline) -> impl FnMut(&mut &'a str) -> ModalResult<&'a str> {
+    use winnow::combinator::{eof, not};
+
+    move |input: &mut &'a str| {
+        preceded(not(eof), terminated(till_line_ending_with(newline), line_ending_or_eof_with(newline)))
+            .context(label("rest line"))
+            .parse_next(input)
+    }
+}
+
+/// Parse a line like [`read_usize_or_eof`], with line ending recognized
+/// according to `newline` policy.
+pub fn read_usize_or_eof_with<'a>(newline: Newline) -> impl FnMut(&mut &'a str) -> ModalResult<usize> {
+    move |s: &mut &'a str| {
+        let p = delimited(space0, unsigned_integer, space0);
+        terminated(p, line_ending_or_eof_with(newline)).parse_next(s)
+    }
+}
+
+/// Parse a line like [`read_usize_many_or_eof`], with line ending recognized
+/// according to `newline` policy.
+pub fn read_usize_many_or_eof_with<'a>(newline: Newline) -> impl FnMut(&mut &'a str) -> ModalResult<Vec<usize>> {
+    move |s: &mut &'a str| {
+        let x = seq! {
+            _: space0,
+            separated(1.., unsigned_integer, space1),
+            _: space0,
+            _: line_ending_or_eof_with(newline),
+        }
+        .parse_next(s)?;
+        Ok(x.0)
+    }
+}
+
+/// Parse a line like [`read_double_or_eof`], with line ending recognized
+/// according to `newline` policy.
+pub fn read_double_or_eof_with<'a>(newline: Newline) -> impl FnMut(&mut &'a str) -> ModalResult<f64> {
+    move |s: &mut &'a str| {
+        let p = delimited(space0, double, space0);
+        terminated(p, line_ending_or_eof_with(newline)).parse_next(s)
+    }
+}
+
+/// Parse a line like [`read_double_many_or_eof`], with line ending
+/// recognized according to `newline` policy.
+pub fn read_double_many_or_eof_with<'a>(newline: Newline) -> impl FnMut(&mut &'a str) -> ModalResult<Vec<f64>> {
+    move |s: &mut &'a str| {
+        let x = seq! {
+            _: space0,
+            separated(1.., double, space1),
+            _: space0,
+            _: line_ending_or_eof_with(newline),
+        }
+        .parse_next(s)?;
+        Ok(x.0)
+    }
+}
+// 6e1fa0c4 ends here
+
 // [[file:../parser.note::838e8dea][838e8dea]]
 /// Convert a string to a float.
 ///
@@ -187,9 +451,211 @@ fn test_fortran_float() {
 }
 // 838e8dea ends here
 
+// [[file:../parser.note::4c9d7e21][4c9d7e21]]
+/// Scan a float number in Fortran style at the beginning of `s`, using
+/// `decimal_mark` as decimal separator. Return the number of bytes consumed
+/// and the parsed value.
+fn scan_fortran_float(s: &str, decimal_mark: char) -> Option<(usize, f64)> {
+    let b = s.as_bytes();
+    let digits = |i: usize| b[i..].iter().take_while(|c| c.is_ascii_digit()).count();
+
+    // numerical problems in output: ****
+    let stars = b.iter().take_while(|&&c| c == b'*').count();
+    if stars > 0 {
+        return Some((stars, f64::NAN));
+    }
+
+    let negative = b.first() == Some(&b'-');
+    let mut i = if matches!(b.first(), Some(b'+' | b'-')) { 1 } else { 0 };
+    for (word, value) in [("infinity", f64::INFINITY), ("inf", f64::INFINITY), ("nan", f64::NAN)] {
+        let j = i + word.len();
+        // not a part of other word, such as "Information"
+        let bounded = !b.get(j).is_some_and(|c| c.is_ascii_alphanumeric());
+        if bounded && s.get(i..j).is_some_and(|x| x.eq_ignore_ascii_case(word)) {
+            let value = if negative { -value } else { value };
+            return Some((i + word.len(), value));
+        }
+    }
+
+    // mantissa
+    let mut txt = s[..i].to_owned();
+    let n_int = digits(i);
+    txt += &s[i..i + n_int];
+    i += n_int;
+    let mut point = false;
+    if s[i..].starts_with(decimal_mark) {
+        let j = i + decimal_mark.len_utf8();
+        let n_frac = digits(j);
+        if n_int + n_frac > 0 {
+            point = true;
+            txt.push('.');
+            txt += &s[j..j + n_frac];
+            i = j + n_frac;
+        }
+    }
+    if !point && n_int == 0 {
+        return None;
+    }
+
+    // exponent: 1.0E-03, 1.0D-03, or 1.0-100 with exponent letter dropped
+    match b.get(i) {
+        Some(b'e' | b'E' | b'd' | b'D') => {
+            let j = if matches!(b.get(i + 1), Some(b'+' | b'-')) { i + 2 } else { i + 1 };
+            let n = digits(j);
+            if n > 0 {
+                txt.push('e');
+                txt += &s[i + 1..j + n];
+                i = j + n;
+            }
+        }
+        Some(b'+' | b'-') if point => {
+            let n = digits(i + 1);
+            // the sign of next number in packed data, such as 1.0-1.5
+            if n > 0 && !s[i + 1 + n..].starts_with(decimal_mark) {
+                txt.push('e');
+                txt += &s[i..i + 1 + n];
+                i += 1 + n;
+            }
+        }
+        _ => {}
+    }
+    txt.parse().ok().map(|x| (i, x))
+}
+
+/// Parse a float number in Fortran or quantum chemistry output, such as
+/// `1.0D-03`, `1.0-100` (exponent letter dropped), `NaN`, `Infinity`, `-Inf`,
+/// or `****` for numerical problems (parsed as NaN).
+pub fn fortran_double(input: &mut &str) -> ModalResult<f64> {
+    fortran_double_with('.').parse_next(input)
+}
+
+/// Parse a float number like [`fortran_double`], with `decimal_mark` as
+/// decimal separator, such as `,` in `1,5D+00`.
+pub fn fortran_double_with<'a>(decimal_mark: char) -> impl FnMut(&mut &'a str) -> ModalResult<f64> {
+    use winnow::error::ErrMode;
+
+    let scan = move |input: &mut &'a str| match scan_fortran_float(input, decimal_mark) {
+        Some((n, x)) => {
+            let _ = input.next_slice(n);
+            Ok(x)
+        }
+        None => Err(ErrMode::from_input(input)),
+    };
+    let mut p = scan.context(label("fortran double"));
+    move |input: &mut &'a str| p.parse_next(input)
+}
+
+/// Consume three float numbers in Fortran style like [`xyz_array`]. The
+/// numbers could be packed without spaces, such as `1.0D+00-2.0D+00`.
+pub fn fortran_xyz_array(s: &mut &str) -> ModalResult<[f64; 3]> {
+    fortran_xyz_array_with('.').parse_next(s)
+}
+
+/// Consume three float numbers like [`fortran_xyz_array`], with
+/// `decimal_mark` as decimal separator.
+pub fn fortran_xyz_array_with<'a>(decimal_mark: char) -> impl FnMut(&mut &'a str) -> ModalResult<[f64; 3]> {
+    move |s: &mut &'a str| {
+        let x = seq! {
+            fortran_double_with(decimal_mark),
+            _: space0,
+            fortran_double_with(decimal_mark),
+            _: space0,
+            fortran_double_with(decimal_mark),
+        }
+        .parse_next(s)?;
+        Ok([x.0, x.1, x.2])
+    }
+}
+
+/// Parse a line containing many float numbers in Fortran style like
+/// [`read_double_many_or_eof`]. The numbers could be packed without spaces,
+/// as in punch files. The last line may end without line ending.
+pub fn read_fortran_double_many(s: &mut &str) -> ModalResult<Vec<f64>> {
+    read_fortran_double_many_with('.', Newline::default()).parse_next(s)
+}
+
+/// Parse a line like [`read_fortran_double_many`], with `decimal_mark` as
+/// decimal separator, and line ending recognized according to `newline`
+/// policy.
+pub fn read_fortran_double_many_with<'a>(decimal_mark: char, newline: Newline) -> impl FnMut(&mut &'a str) -> ModalResult<Vec<f64>> {
+    move |s: &mut &'a str| {
+        let x = seq! {
+            repeat(1.., preceded(space0, fortran_double_with(decimal_mark))),
+            _: space0,
+            _: line_ending_or_eof_with(newline),
+        }
+        .parse_next(s)?;
+        Ok(x.0)
+    }
+}
+
+#[test]
+fn test_fortran_double() {
+    let p = |s| fortran_double.parse_peek(s).unwrap();
+    assert_eq!(p("14.12D4"), ("", 14.12E4));
+    assert_eq!(p("1.0D-03 x"), (" x", 1.0E-3));
+    assert_eq!(p("-1.0-100"), ("", -1.0E-100));
+    assert_eq!(p("1.0+05"), ("", 1.0E5));
+    assert_eq!(p("1.0-1.5"), ("-1.5", 1.0));
+    assert_eq!(p("1-2"), ("-2", 1.0));
+    assert_eq!(p(".5e1"), ("", 5.0));
+    assert_eq!(p("-Inf"), ("", f64::NEG_INFINITY));
+    assert_eq!(p("Infinity"), ("", f64::INFINITY));
+    assert_eq!(p("1.5E"), ("E", 1.5));
+    assert!(p("NaN").1.is_nan());
+    assert!(p("-nan").1.is_nan());
+    assert!(p("*****").1.is_nan());
+    assert!(fortran_double.parse_peek("x").is_err());
+    assert!(fortran_double.parse_peek("-.").is_err());
+    assert!(fortran_double.parse_peek("").is_err());
+    // not a part of other word
+    assert!(fortran_double.parse_peek("Information").is_err());
+    assert!(fortran_double.parse_peek("nano").is_err());
+    assert!(fortran_double.parse_peek("Infinity2").is_err());
+    assert_eq!(p("inf,"), (",", f64::INFINITY));
+    assert!(p("NaN)").1.is_nan());
+
+    // decimal comma
+    let (rest, x) = fortran_double_with(',').parse_peek("1,5D+00 2,5").unwrap();
+    assert_eq!((rest, x), (" 2,5", 1.5));
+    let (rest, x) = fortran_double.parse_peek("1,5").unwrap();
+    assert_eq!((rest, x), (",5", 1.0));
+
+    // packed numbers in punch files
+    let line = " 0.12345678D+00-0.23456789D+00 1.0-100\n";
+    let (rest, xs) = read_fortran_double_many.parse_peek(line).unwrap();
+    assert_eq!(rest, "");
+    assert_eq!(xs, [0.12345678, -0.23456789, 1.0E-100]);
+    assert!(read_fortran_double_many.parse_peek("1.0 2.0\r3.0\n").is_err());
+    let (rest, xs) = read_fortran_double_many_with('.', Newline::Universal).parse_peek("1.0 2.0\r3.0\n").unwrap();
+    assert_eq!((rest, xs), ("3.0\n", vec![1.0, 2.0]));
+    let (_, xyz) = fortran_xyz_array.parse_peek("1.0D+00-2.0D+00 NaN").unwrap();
+    assert_eq!(xyz[..2], [1.0, -2.0]);
+    assert!(xyz[2].is_nan());
+    let (_, xyz) = fortran_xyz_array_with(',').parse_peek("1,0D+00-2,0D+00 3,5").unwrap();
+    assert_eq!(xyz, [1.0, -2.0, 3.5]);
+    let (rest, xs) = read_fortran_double_many_with(',', Newline::default()).parse_peek("1,5 -2,5D+01\nx").unwrap();
+    assert_eq!((rest, xs), ("x", vec![1.5, -25.0]));
+
+    // the last line without line ending
+    assert_eq!(read_fortran_double_many.parse_peek("1.0 2.0"), Ok(("", vec![1.0, 2.0])));
+    assert!(read_fortran_double_many.parse_peek("").is_err());
+
+    // array in fchk file
+    let txt = gut::fs::read_file("./tests/files/Test.FChk").unwrap();
+    let mut s = txt.as_str();
+    jump_to("Current cartesian coordinates").parse_next(&mut s).unwrap();
+    rest_line.parse_next(&mut s).unwrap();
+    let lines: Vec<Vec<f64>> = repeat(1.., read_fortran_double_many).parse_next(&mut s).unwrap();
+    let coords: Vec<_> = lines.concat();
+    assert_eq!(coords.len(), 33);
+    assert_eq!(coords[0], 2.37615024E+00);
+}
+// 4c9d7e21 ends here
+
 // [[file:../parser.note::10e5dba2][10e5dba2]]
 #[test]
-fn test_ws() -> PResult<()> {
+fn test_ws() -> ModalResult<()> {
     let s = " 123 ";
     let (_, x) = ws(digit1).parse_peek(s)?;
     assert_eq!(x, "123");
@@ -250,6 +716,22 @@ fn test_read_line() {
     assert!(x.is_err());
 }
 
+#[test]
+fn test_read_line_with() {
+    let txt = "first\rsecond\r\nthird";
+    let (rest, line) = read_line_with(Newline::Universal).parse_peek(txt).unwrap();
+    assert_eq!(line, "first\r");
+    let (rest, line) = rest_line_with(Newline::Universal).parse_peek(rest).unwrap();
+    assert_eq!(line, "second");
+    assert_eq!(rest, "third");
+    assert!(rest_line_with(Newline::Universal).parse_peek(rest).is_err());
+
+    // a single CR is not line ending by default
+    assert!(rest_line_with(Newline::Preserve).parse_peek(txt).is_err());
+    let (rest, line) = rest_line_with(Newline::Normalize).parse_peek("a\r\nb").unwrap();
+    assert_eq!((line, rest), ("a", "b"));
+}
+
 #[test]
 fn test_read_many() {
     let (_, ns) = read_usize_many.parse_peek("11 2 3 4 5\r\n\n").expect("usize parser");
@@ -262,6 +744,48 @@ fn test_read_many() {
     assert_eq!(4, fs.len());
 }
 
+#[test]
+fn test_read_or_eof() {
+    // the last line without line ending
+    assert!(read_usize.parse_peek(" 12 ").is_err());
+    assert_eq!(read_usize_or_eof.parse_peek(" 12 "), Ok(("", 12)));
+    assert_eq!(read_usize_or_eof.parse_peek(" 12 \r\nx"), Ok(("x", 12)));
+    assert_eq!(read_double_or_eof.parse_peek("1.5"), Ok(("", 1.5)));
+    assert_eq!(read_usize_many_or_eof.parse_peek("1 2 3"), Ok(("", vec![1, 2, 3])));
+    assert_eq!(read_double_many_or_eof.parse_peek("1.0 -2"), Ok(("", vec![1.0, -2.0])));
+    assert!(read_double_many_or_eof.parse_peek("1.0 x").is_err());
+    assert!(rest_line.parse_peek("last").is_err());
+    assert_eq!(rest_line_or_eof.parse_peek("last"), Ok(("", "last")));
+    assert_eq!(rest_line_or_eof.parse_peek("first\nlast"), Ok(("last", "first")));
+    assert!(rest_line_or_eof.parse_peek("").is_err());
+
+    // a single CR ends a line only in universal mode
+    let newline = Newline::Universal;
+    assert!(read_usize_or_eof.parse_peek("12\r3").is_err());
+    assert_eq!(read_usize_or_eof_with(newline).parse_peek("12\r3"), Ok(("3", 12)));
+    assert_eq!(read_double_or_eof_with(newline).parse_peek("1.5\r\n"), Ok(("", 1.5)));
+    assert_eq!(read_usize_many_or_eof_with(newline).parse_peek("1 2\r3"), Ok(("3", vec![1, 2])));
+    assert_eq!(read_double_many_or_eof_with(newline).parse_peek("1 2\r3"), Ok(("3", vec![1.0, 2.0])));
+    assert!(rest_line_or_eof.parse_peek("a\rb").is_err());
+    assert_eq!(rest_line_or_eof_with(newline).parse_peek("a\rb"), Ok(("b", "a")));
+    assert_eq!(line_ending_or_eof_with(newline).parse_peek("\r"), Ok(("", "\r")));
+
+    // take all lines, with or without final line ending
+    let lines = |s| repeat(0.., rest_line_or_eof).parse(s).map(|x: Vec<&str>| x.len()).unwrap();
+    assert_eq!(lines("a\nb\n"), 2);
+    assert_eq!(lines("a\nb"), 2);
+    assert_eq!(lines(""), 0);
+
+    // the last part from partitions without final newline
+    use crate::partition::FixedLines;
+    use crate::TextReader;
+    let parts: Vec<_> = TextReader::from_str("1 2\n3 4").partitions(FixedLines(1)).collect();
+    for part in parts {
+        let xs = read_usize_many_or_eof.parse(part.as_str()).unwrap();
+        assert_eq!(xs.len(), 2);
+    }
+}
+
 #[test]
 fn test_signed_digit() {
     let (_, x) = signed_digit.parse_peek("-123").expect("signed digit, minus");
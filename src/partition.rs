@@ -40,6 +40,8 @@
 
 use std::io::prelude::*;
 
+use crate::limits::LimitError;
+use crate::newline::Newline;
 use crate::reader::TextReader;
 use gut::prelude::*;
 // fa39dec3 ends here
@@ -49,50 +51,121 @@ use gut::prelude::*;
 pub struct ReadContext<'a> {
     /// Buffered text.
     chunk: &'a str,
-    /// byte size for each line of text.
-    nlist: &'a [usize],
+    /// byte offsets in `chunk` for the start of each line, followed by the end
+    /// of the last line.
+    offsets: &'a [usize],
 }
 
 impl<'a> ReadContext<'a> {
-    pub(crate) fn new(buf: &'a str, nlist: &'a [usize]) -> Self {
-        Self { chunk: buf, nlist }
+    /// Construct from text in `buf` with line offsets in `offsets`, which
+    /// contains one more item than the number of lines.
+    pub(crate) fn new(buf: &'a str, offsets: &'a [usize]) -> Self {
+        debug_assert!(!offsets.is_empty());
+        Self { chunk: buf, offsets }
     }
 
     /// Return the number of lines that already read in.
     #[inline]
     pub fn number_of_lines(&self) -> usize {
-        self.nlist.len()
+        self.offsets.len() - 1
     }
 
     /// Return the line numbered as `n` (1-based)
     ///
     /// # Panic
     ///
-    /// * Panics if index `n` out of bounds.
-    pub fn line(&self, n: usize) -> &str {
-        assert_ne!(n, 0, "invalid line number");
-        let n = n - 1;
-        let ns = self.nlist[0..n].iter().sum();
-        let nb = self.nlist[n];
-        &self.chunk[ns..ns + nb]
+    /// * Panics if index `n` out of bounds. See also [`ReadContext::try_line`].
+    #[inline]
+    pub fn line(&self, n: usize) -> &'a str {
+        self.try_line(n).unwrap_or_else(|e| panic!("{e}"))
+    }
+
+    /// Return the line numbered as `n` (1-based). Return error if `n` out of
+    /// bounds.
+    pub fn try_line(&self, n: usize) -> Result<&'a str> {
+        let m = self.number_of_lines();
+        self.get_line(n).ok_or_else(|| anyhow!("invalid line number {n} for {m} lines"))
+    }
+
+    /// Return the line numbered as `n` (1-based). Return None if `n` out of
+    /// bounds.
+    #[inline]
+    pub fn get_line(&self, n: usize) -> Option<&'a str> {
+        if n == 0 || n >= self.offsets.len() {
+            return None;
+        }
+        Some(&self.chunk[self.offsets[n - 1]..self.offsets[n]])
+    }
+
+    /// Return the last line. Return None if there is no line.
+    #[inline]
+    pub fn last_line(&self) -> Option<&'a str> {
+        self.get_line(self.number_of_lines())
+    }
+
+    /// Returns an iterator over all lines, including line endings.
+    pub fn lines(&self) -> impl Iterator<Item = &'a str> + '_ {
+        self.offsets.windows(2).map(|w| &self.chunk[w[0]..w[1]])
+    }
+
+    /// Return the byte range of line `n` (1-based) in `text`. Return None if
+    /// `n` out of bounds.
+    #[inline]
+    pub fn line_range(&self, n: usize) -> Option<std::ops::Range<usize>> {
+        if n == 0 || n >= self.offsets.len() {
+            return None;
+        }
+        let base = self.offsets[0];
+        Some(self.offsets[n - 1] - base..self.offsets[n] - base)
+    }
+
+    /// Return the total number of bytes of all lines.
+    #[inline]
+    pub fn num_bytes(&self) -> usize {
+        self.offsets[self.offsets.len() - 1] - self.offsets[0]
     }
 
     /// Return buffered text.
-    pub fn text(&self) -> &str {
-        &self.chunk
+    pub fn text(&self) -> &'a str {
+        &self.chunk[self.offsets[0]..self.offsets[self.offsets.len() - 1]]
+    }
+
+    /// Return the context without the first `k` lines.
+    pub(crate) fn skip_lines(&self, k: usize) -> Self {
+        Self {
+            chunk: self.chunk,
+            offsets: &self.offsets[k..],
+        }
     }
 }
 
 #[test]
 fn test_read_context() {
     let txt = "line1\nLine2\nline 3\n";
-    let nlist: Vec<_> = txt.lines().map(|l| l.len() + 1).collect();
-    let context = ReadContext::new(txt, &nlist);
+    let offsets = [0, 6, 12, 19];
+    let context = ReadContext::new(txt, &offsets);
 
     assert_eq!(context.number_of_lines(), 3);
     assert_eq!(context.line(1), "line1\n");
     assert_eq!(context.line(2), "Line2\n");
     assert_eq!(context.line(3), "line 3\n");
+    assert_eq!(context.get_line(0), None);
+    assert_eq!(context.get_line(4), None);
+    assert_eq!(context.last_line(), Some("line 3\n"));
+    assert_eq!(context.lines().count(), 3);
+    assert_eq!(context.num_bytes(), txt.len());
+
+    let context = context.skip_lines(1);
+    assert_eq!(context.number_of_lines(), 2);
+    assert_eq!(context.line(1), "Line2\n");
+    assert_eq!(context.line_range(2), Some(6..13));
+    assert_eq!(context.text(), "Line2\nline 3\n");
+    assert_eq!(context.num_bytes(), 13);
+
+    let context = context.skip_lines(2);
+    assert_eq!(context.number_of_lines(), 0);
+    assert_eq!(context.last_line(), None);
+    assert_eq!(context.text(), "");
 }
 
 /// Read text stream at line basis
@@ -103,12 +176,55 @@ pub enum ReadAction {
     Done(usize),
     /// Error description
     Error(String),
+    /// Drop the first n lines, such as junk lines between parts
+    Skip(usize),
+    /// Finish reading parts, ignoring the remaining text
+    Stop,
+    /// Error found in the line numbered as `line` in `ReadContext`
+    Fail(PartError),
 }
 
+/// Error found when reading parts of text.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PartError {
+    /// The line number where the error occurs (1-based). It is relative to
+    /// `ReadContext` when returned in `ReadAction`, and is absolute in the
+    /// text stream when reported by `Partitions`.
+    pub line: usize,
+    /// Error description
+    pub message: String,
+    // the limit exceeded
+    limit: Option<LimitError>,
+}
+
+impl PartError {
+    /// Construct an error found in line `line` with `message`.
+    pub fn new(line: usize, message: impl Into<String>) -> Self {
+        Self {
+            line,
+            message: message.into(),
+            limit: None,
+        }
+    }
+
+    /// Return the limit exceeded if reading stopped by resource limits.
+    pub fn limit_error(&self) -> Option<&LimitError> {
+        self.limit.as_ref()
+    }
+}
+
+impl std::fmt::Display for PartError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "line {}: {}", self.line, self.message)
+    }
+}
+
+impl std::error::Error for PartError {}
+
 /// Instruct the reader how to read a part of text by inspecting `ReadContext`
 pub trait ReadPart {
     /// How to read next lines?
-    fn read_next<'a>(&self, context: ReadContext<'a>) -> ReadAction {
+    fn read_next<'a>(&self, _context: ReadContext<'a>) -> ReadAction {
         ReadAction::Need(1)
     }
 
@@ -116,10 +232,50 @@ pub trait ReadPart {
     fn n_stride(&self) -> usize {
         1
     }
+
+    /// Clear the state kept for reading current part. It is called when a
+    /// part is finished, including the last part at EOF, which is taken
+    /// without calling `read_next`.
+    fn reset(&self) {}
 }
 // de2a5565 ends here
 
 // [[file:../parser.note::11167470][11167470]]
+/// A part of text with its location in the original stream.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TextPart {
+    /// The index of this part, counting from 0.
+    pub index: usize,
+    /// The absolute position in bytes of the start of this part.
+    pub start: u64,
+    /// The absolute position in bytes of the end of this part (exclusive).
+    pub end: u64,
+    /// The line number of the first line (1-based).
+    pub first_line: usize,
+    /// The line number of the last line (1-based).
+    pub last_line: usize,
+    /// The text of this part.
+    pub text: String,
+    // the absolute positions of line starts, and the end of last line
+    positions: Vec<u64>,
+}
+
+impl TextPart {
+    /// Return the byte range of this part in original stream.
+    pub fn byte_range(&self) -> std::ops::Range<u64> {
+        self.start..self.end
+    }
+
+    /// Return a short description of the location of this part, suitable
+    /// for error message.
+    pub fn location(&self) -> String {
+        format!(
+            "part {} (lines {}-{}, bytes {}..{})",
+            self.index, self.first_line, self.last_line, self.start, self.end
+        )
+    }
+}
+
 /// An iterator over part of text stream.
 pub struct Partitions<R, P>
 where
@@ -128,78 +284,284 @@ where
     reader: TextReader<R>,
     part: P,
     buf: String,
-    nlist: Vec<usize>,
+    // byte offsets in `buf` for each line start, and the end of last line
+    offsets: Vec<usize>,
+    // the same as `offsets`, but in absolute positions of the original
+    // stream, which could differ due to line ending conversion.
+    positions: Vec<u64>,
+    // the number of lines to skip before reading any part
+    preamble: usize,
+    // the number of parts read
+    nparts: usize,
+    // stop after reading this number of parts
+    max_parts: Option<usize>,
+    // the number of lines taken out from buffer
+    nlines: usize,
+    // the number of lines lent out in `next_text`, to be dropped in next read
+    lent: usize,
+    // no more parts to read
+    finished: bool,
+    // the error stopped reading
+    error: Option<PartError>,
+    // progress reporting and cancellation
+    monitor: Option<Monitor>,
 }
 
 impl<R: BufRead, P> Partitions<R, P> {
-    fn new(reader: TextReader<R>, part: P) -> Self {
+    fn new(mut reader: TextReader<R>, part: P) -> Self {
+        // parts use Unix line ending, unless required otherwise
+        reader.newline.get_or_insert(Newline::Normalize);
         Self {
             reader,
             part,
             buf: String::new(),
-            nlist: vec![],
+            offsets: vec![0],
+            positions: vec![0],
+            preamble: 0,
+            nparts: 0,
+            max_parts: None,
+            nlines: 0,
+            lent: 0,
+            finished: false,
+            error: None,
+            monitor: None,
+        }
+    }
+
+    /// Return the error that stopped reading parts, with the absolute line
+    /// number in text stream.
+    pub fn error(&self) -> Option<&PartError> {
+        self.error.as_ref()
+    }
+
+    /// Return the number of lines in buffer.
+    fn buffered_lines(&self) -> usize {
+        self.offsets.len() - 1
+    }
+
+    /// Drop the first `n` lines in buffer.
+    fn drop_lines(&mut self, n: usize) {
+        let n = n.min(self.buffered_lines());
+        let ns = self.offsets[n];
+        self.buf.drain(0..ns);
+        self.offsets.drain(0..n);
+        self.offsets.iter_mut().for_each(|x| *x -= ns);
+        self.positions.drain(0..n);
+        self.nlines += n;
+    }
+
+    /// Take the text of the first `n` lines out from buffer, without
+    /// metadata.
+    fn take_text(&mut self, n: usize) -> String {
+        let n = n.min(self.buffered_lines());
+        let text = self.buf[..self.offsets[n]].to_owned();
+        self.drop_lines(n);
+        text
+    }
+
+    /// Take the first `n` lines out from buffer as the last part read.
+    fn take_lines(&mut self, n: usize) -> TextPart {
+        let n = n.min(self.buffered_lines());
+        let part = TextPart {
+            index: self.nparts.saturating_sub(1),
+            start: self.positions[0],
+            end: self.positions[n],
+            first_line: self.nlines + 1,
+            last_line: self.nlines + n,
+            text: self.buf[..self.offsets[n]].to_owned(),
+            positions: self.positions[..=n].to_vec(),
+        };
+        self.drop_lines(n);
+        part
+    }
+
+    /// Set the position (in bytes) and the line number of the next line to
+    /// read in the original stream, when the reader does not start from the
+    /// beginning. This only affects metadata in [`TextPart`]. Partitioning
+    /// fails if any line has already been read.
+    pub fn starting_at(mut self, position: u64, line: usize) -> Self {
+        if self.buffered_lines() > 0 || self.nlines > 0 {
+            self.fail(0, "starting position set after reading".into());
+            return self;
+        }
+        self.positions = vec![position];
+        self.nlines = line.saturating_sub(1);
+        self
+    }
+
+    /// Stop reading with exceeded `limit` in line `line` of buffered text.
+    fn fail_with_limit(&mut self, line: usize, limit: LimitError) {
+        self.fail(line, limit.to_string());
+        if let Some(e) = self.error.as_mut() {
+            e.limit = Some(limit);
         }
     }
 
+    /// Stop reading with error in line `line` of buffered text.
+    fn fail(&mut self, line: usize, message: String) {
+        let e = PartError::new(self.nlines + line, message);
+        error!("partition failure: {}", e);
+        self.error = Some(e);
+        self.finished = true;
+    }
+
+    /// Skip the first `n` lines as preamble before reading any part.
+    pub fn skip_preamble(mut self, n: usize) -> Self {
+        self.preamble = n;
+        self
+    }
+
+    /// Stop after reading `n` parts.
+    pub fn max_parts(mut self, n: usize) -> Self {
+        self.max_parts = Some(n);
+        self
+    }
+
     /// Read in `n` lines into `buf`. Return the number of bytes read in total.
     ///
     /// # NOTE
-    /// - The new line is forced to use unix style line ending.
+    /// - CRLF is converted to LF, unless other newline policy set in reader.
     fn read_n_lines(&mut self, n: usize) -> Option<usize> {
-        assert_ne!(n, 0);
         for _ in 0..n {
-            let mut m = self.reader.read_line(&mut self.buf)?;
-            // force to use Unix line ending
-            if self.buf.ends_with("\r\n") {
-                let i = self.buf.len() - 2;
-                self.buf.remove(i);
-                // we removed one byte
-                m -= 1;
+            let m = match self.reader.read_line(&mut self.buf) {
+                Ok(0) => return None,
+                Ok(m) => m,
+                Err(e) => {
+                    let line = self.buffered_lines() + 1;
+                    match e.downcast_ref::<LimitError>() {
+                        Some(&limit) => self.fail_with_limit(line, limit),
+                        None => self.fail(line, format!("{e:#}")),
+                    }
+                    return None;
+                }
+            };
+            if let Err(limit) = self.reader.limits.check_buffered(self.buf.len()) {
+                self.fail_with_limit(self.buffered_lines() + 1, limit);
+                return None;
             }
-
-            self.nlist.push(m);
+            let pos = self.positions[self.positions.len() - 1];
+            self.positions.push(pos + m as u64);
+            self.offsets.push(self.buf.len());
         }
-        return Some(self.nlist.iter().sum());
+        Some(self.buf.len())
     }
 
     /// build ReadContext for client.
-    fn context(&self) -> ReadContext {
-        ReadContext::new(&self.buf, &self.nlist)
+    fn context(&self) -> ReadContext<'_> {
+        ReadContext::new(&self.buf, &self.offsets)
     }
 }
 
-impl<R: BufRead, P: ReadPart> Iterator for Partitions<R, P> {
-    type Item = String;
+impl<R: BufRead, P: ReadPart> Partitions<R, P> {
+    /// Read in lines until the next part is found in the front of buffer.
+    /// Return the number of lines in the part.
+    fn advance(&mut self) -> Option<usize> {
+        let n = std::mem::take(&mut self.lent);
+        self.drop_lines(n);
+        if self.finished || self.max_parts.is_some_and(|n| self.nparts >= n) {
+            return None;
+        }
+        if self.preamble > 0 {
+            let n = std::mem::take(&mut self.preamble);
+            let _ = self.read_n_lines(n);
+            self.drop_lines(n);
+        }
 
-    fn next(&mut self) -> Option<Self::Item> {
         // read next n lines
-        let mut m = self.part.n_stride();
-        loop {
-            // let _ = self.read_n_lines(m)?;
-            // process the last part when reaching EOF
-            if self.read_n_lines(m).is_none() {
-                if self.buf.is_empty() {
+        let mut m = self.part.n_stride().max(1);
+        let found = loop {
+            // process the last part when reaching EOF. No more lines to read
+            // in when m is zero.
+            if m > 0 && self.read_n_lines(m).is_none() {
+                if self.buf.is_empty() || self.finished {
+                    break None;
+                } else if let Err(limit) = self.reader.limits.check_part_size(self.buf.len()) {
+                    self.fail_with_limit(self.buffered_lines(), limit);
                     break None;
                 } else {
-                    break Some(self.buf.drain(..).collect());
+                    self.nparts += 1;
+                    break Some(self.buffered_lines());
                 }
             }
             // read in enough number of lines?
             match self.part.read_next(self.context()) {
-                ReadAction::Need(n) => m = n,
+                ReadAction::Need(n) => m = n.max(1),
+                // an empty part takes no line, and will be read forever
+                ReadAction::Done(0) => {
+                    self.fail(1, "empty part".into());
+                    break None;
+                }
                 ReadAction::Done(n) => {
-                    // take the first `n` lines as a part
-                    let ns = self.nlist.drain(0..n).sum();
-                    break Some(self.buf.drain(0..ns).collect());
+                    // the first `n` lines as a part
+                    let n = n.min(self.buffered_lines());
+                    if let Err(limit) = self.reader.limits.check_part_size(self.offsets[n]) {
+                        self.fail_with_limit(n, limit);
+                        break None;
+                    }
+                    self.nparts += 1;
+                    break Some(n);
+                }
+                ReadAction::Skip(n) => {
+                    self.drop_lines(n);
+                    // inspect the remaining lines before reading in more,
+                    // unless no line skipped
+                    m = if n == 0 || self.buffered_lines() == 0 { self.part.n_stride().max(1) } else { 0 };
+                }
+                ReadAction::Stop => {
+                    self.finished = true;
+                    break None;
                 }
                 ReadAction::Error(s) => {
-                    error!("partition failure: {}", s);
+                    self.fail(self.buffered_lines(), s);
+                    break None;
+                }
+                ReadAction::Fail(e) => {
+                    self.fail(e.line, e.message);
                     break None;
                 }
-                _ => unreachable!(),
             }
+        };
+        // reading may stop in the middle of a part, such as at EOF
+        self.part.reset();
+        found
+    }
+
+    /// Read the next part with its metadata.
+    pub fn next_part(&mut self) -> Option<TextPart> {
+        let n = self.monitored_advance()?;
+        Some(self.take_lines(n))
+    }
+
+    /// Read the next part without allocation. The returned text borrows the
+    /// internal buffer, which will be reused in the next read.
+    pub fn next_text(&mut self) -> Option<&str> {
+        let n = self.monitored_advance()?;
+        self.lent = n;
+        Some(&self.buf[..self.offsets[n]])
+    }
+
+    /// Call `f` on the text of each part without allocation.
+    pub fn for_each_text<F: FnMut(&str)>(mut self, mut f: F) {
+        while let Some(text) = self.next_text() {
+            f(text);
         }
     }
+
+    /// Returns an iterator over parts with metadata, including part index,
+    /// byte offsets and line numbers.
+    pub fn with_metadata(mut self) -> impl Iterator<Item = TextPart> {
+        std::iter::from_fn(move || self.next_part())
+    }
+}
+
+impl<R: BufRead, P: ReadPart> Iterator for Partitions<R, P> {
+    type Item = String;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        // line positions are only required in metadata
+        let n = self.monitored_advance()?;
+        Some(self.take_text(n))
+    }
 }
 
 impl<R: BufRead> TextReader<R> {
@@ -222,7 +584,7 @@ impl<R: BufRead> TextReader<R> {
             let mut s = String::new();
             for i in 0..n {
                 // special treatment for the last line
-                match (i, self.read_line(&mut s)) {
+                match (i, self.read_line(&mut s).ok().filter(|&n| n > 0)) {
                     (_, Some(_)) => {}
                     (0, None) => return None,
                     (_, None) => return Some(s),
@@ -231,6 +593,29 @@ impl<R: BufRead> TextReader<R> {
             Some(s)
         })
     }
+
+    /// Call `f` on each part of text in `n` lines, reusing the same buffer
+    /// without allocation. Return the number of parts read.
+    pub fn for_each_chunk<F>(&mut self, n: usize, mut f: F) -> Result<usize>
+    where
+        F: FnMut(&str) -> Result<()>,
+    {
+        let mut s = String::new();
+        let mut nparts = 0;
+        loop {
+            s.clear();
+            for _ in 0..n {
+                if self.read_line(&mut s)? == 0 {
+                    break;
+                }
+            }
+            if s.is_empty() {
+                return Ok(nparts);
+            }
+            f(&s)?;
+            nparts += 1;
+        }
+    }
 }
 // 9f67096e ends here
 
@@ -341,6 +726,522 @@ fn test_preceded() -> Result<()> {
 }
 // 2b9d1c8d ends here
 
+// [[file:../parser.note::4d8b2e6f][4d8b2e6f]]
+/// Read parts in fixed number of lines. Partitioning fails if the number of
+/// lines is zero.
+#[derive(Debug, Clone)]
+pub struct FixedLines(pub usize);
+
+impl ReadPart for FixedLines {
+    #[inline]
+    fn read_next(&self, context: ReadContext) -> ReadAction {
+        let n = context.number_of_lines();
+        if self.0 == 0 {
+            ReadAction::Error("invalid number of lines for a part: 0".into())
+        } else if n >= self.0 {
+            ReadAction::Done(self.0)
+        } else {
+            ReadAction::Need(self.0 - n)
+        }
+    }
+
+    fn n_stride(&self) -> usize {
+        self.0.max(1)
+    }
+}
+
+/// Each part has a count line numbered as `line` (1-based), and the count
+/// gives the number of lines following it, plus `extra` lines. For example,
+/// the xyz format has count on line 1 with one extra comment line.
+#[derive(Debug, Clone)]
+pub struct CountHeader {
+    /// The line number of the count line, counting from 1.
+    pub line: usize,
+    /// The number of lines more than the count.
+    pub extra: usize,
+}
+
+impl CountHeader {
+    /// Count on the first line, followed by one comment line, as in xyz
+    /// format.
+    pub fn xyz() -> Self {
+        Self { line: 1, extra: 1 }
+    }
+}
+
+impl ReadPart for CountHeader {
+    fn read_next(&self, context: ReadContext) -> ReadAction {
+        let n = context.number_of_lines();
+        if n < self.line {
+            return ReadAction::Need(self.line - n);
+        }
+        let Some(line) = context.get_line(self.line) else {
+            return ReadAction::Fail(PartError::new(self.line, "invalid count line number"));
+        };
+        match line.trim().parse::<usize>() {
+            Ok(count) => {
+                let m = self.line.saturating_add(count).saturating_add(self.extra);
+                if n >= m {
+                    ReadAction::Done(m)
+                } else {
+                    ReadAction::Need(m - n)
+                }
+            }
+            Err(_) => ReadAction::Fail(PartError::new(self.line, format!("invalid count line: {line:?}"))),
+        }
+    }
+
+    fn n_stride(&self) -> usize {
+        self.line.max(1)
+    }
+}
+
+/// Parts separated by blank lines, as in pxyz format. The separating blank
+/// line is included in the part.
+#[derive(Debug, Clone)]
+pub struct BlankSeparated;
+
+impl ReadPart for BlankSeparated {
+    #[inline]
+    fn read_next(&self, context: ReadContext) -> ReadAction {
+        let n = context.number_of_lines();
+        let blank = |i| context.line(i).trim().is_empty();
+        if blank(n) && (1..n).any(|i| !blank(i)) {
+            ReadAction::Done(n)
+        } else {
+            ReadAction::Need(1)
+        }
+    }
+}
+
+/// Started with a header line matching the first closure, and ended with a
+/// footer line matching the second closure. Lines outside of header and
+/// footer are skipped.
+pub struct HeaderFooter<H, F>(pub H, pub F);
+
+impl<H, F> ReadPart for HeaderFooter<H, F>
+where
+    H: Fn(&str) -> bool,
+    F: Fn(&str) -> bool,
+{
+    fn read_next(&self, context: ReadContext) -> ReadAction {
+        let n = context.number_of_lines();
+        if !(self.0)(context.line(1)) {
+            return ReadAction::Skip(1);
+        }
+        if n > 1 && (self.1)(context.line(n)) {
+            ReadAction::Done(n)
+        } else {
+            ReadAction::Need(1)
+        }
+    }
+}
+
+/// Terminated with a delimiter line, such as "$$$$" in SDF format. Leading or
+/// trailing whitespace in delimiter line is ignored.
+#[derive(Debug, Clone)]
+pub struct Delimited(pub String);
+
+impl ReadPart for Delimited {
+    #[inline]
+    fn read_next(&self, context: ReadContext) -> ReadAction {
+        let n = context.number_of_lines();
+        if context.line(n).trim() == self.0 {
+            ReadAction::Done(n)
+        } else {
+            ReadAction::Need(1)
+        }
+    }
+}
+
+/// Combine every `n` consecutive parts read by the inner partitioner into one
+/// part. Lines skipped by inner partitioner are kept if they are between two
+/// inner parts.
+pub struct Repeat<P> {
+    inner: P,
+    n: usize,
+    // the number of lines and parts already read by the inner partitioner in
+    // current part, as the inner partitioner may only inspect the last line
+    read: std::cell::Cell<(usize, usize)>,
+}
+
+impl<P: ReadPart> Repeat<P> {
+    /// Combine every `n` parts read by partitioner `p`.
+    pub fn new(p: P, n: usize) -> Self {
+        Self {
+            inner: p,
+            n,
+            read: Default::default(),
+        }
+    }
+}
+
+impl<P: ReadPart> ReadPart for Repeat<P> {
+    fn read_next(&self, context: ReadContext) -> ReadAction {
+        let (mut k, mut i) = self.read.get();
+        while i < self.n {
+            let sub = context.skip_lines(k);
+            if sub.number_of_lines() == 0 {
+                break;
+            }
+            match self.inner.read_next(sub) {
+                ReadAction::Done(m) => {
+                    k += m;
+                    i += 1;
+                }
+                // lines skipped between inner parts are kept in combined part
+                ReadAction::Skip(m) if k > 0 && m > 0 => k += m.min(context.number_of_lines() - k),
+                ReadAction::Fail(mut e) => {
+                    self.read.set((0, 0));
+                    e.line += k;
+                    return ReadAction::Fail(e);
+                }
+                action => {
+                    self.read.set((k, i));
+                    return action;
+                }
+            }
+        }
+        if i < self.n {
+            self.read.set((k, i));
+            ReadAction::Need(self.inner.n_stride())
+        } else {
+            self.read.set((0, 0));
+            ReadAction::Done(k)
+        }
+    }
+
+    fn n_stride(&self) -> usize {
+        self.inner.n_stride()
+    }
+
+    fn reset(&self) {
+        self.read.set((0, 0));
+        self.inner.reset();
+    }
+}
+// 4d8b2e6f ends here
+
+// [[file:../parser.note::e7d04b96][e7d04b96]]
+use crate::reader::Checkpoint;
+
+impl<R: BufRead, P> Partitions<R, P> {
+    /// Return the cursor state for resuming, including the text read in
+    /// but not taken as a part yet.
+    ///
+    /// The byte offsets are correct only when the reader starts from the
+    /// beginning of stream, or the start is set by [`Partitions::starting_at`].
+    pub fn checkpoint(&self) -> Checkpoint {
+        let n = self.lent;
+        Checkpoint {
+            position: self.positions[self.positions.len() - 1],
+            line: Some(self.nlines + self.buffered_lines()),
+            part_index: self.nparts,
+            pending: self.buf[self.offsets[n]..].to_owned(),
+            pending_positions: self.positions[n..self.positions.len() - 1].to_vec(),
+        }
+    }
+}
+
+impl<R: BufRead + Seek, P> Partitions<R, P> {
+    /// Resume partitioning from the state in `checkpoint` using `reader`
+    /// and partitioner `part`.
+    pub fn resume(mut reader: TextReader<R>, part: P, checkpoint: &Checkpoint) -> Result<Self> {
+        let line = checkpoint.line.ok_or_else(|| anyhow!("line number is required for resuming"))?;
+        let pending = &checkpoint.pending;
+        let mut offsets = vec![0];
+        offsets.extend(pending.match_indices('\n').map(|(i, _)| i + 1));
+        if offsets.last() != Some(&pending.len()) {
+            offsets.push(pending.len());
+        }
+        let mut positions = checkpoint.pending_positions.clone();
+        positions.push(checkpoint.position);
+        ensure!(offsets.len() == positions.len(), "inconsistent pending text in checkpoint");
+        ensure!(line >= offsets.len() - 1, "invalid line number in checkpoint");
+        reader.goto(checkpoint.position)?;
+
+        let mut parts = Self::new(reader, part);
+        parts.buf = pending.to_owned();
+        parts.offsets = offsets;
+        parts.positions = positions;
+        parts.nlines = line - (parts.offsets.len() - 1);
+        parts.nparts = checkpoint.part_index;
+        Ok(parts)
+    }
+}
+// e7d04b96 ends here
+
+// [[file:../parser.note::c3a96e1d][c3a96e1d]]
+use std::io::Cursor;
+
+impl<P: ReadPart + ?Sized> ReadPart for &P {
+    fn read_next(&self, context: ReadContext) -> ReadAction {
+        (**self).read_next(context)
+    }
+
+    fn n_stride(&self) -> usize {
+        (**self).n_stride()
+    }
+
+    fn reset(&self) {
+        (**self).reset()
+    }
+}
+
+/// An iterator over parts inside a parent [`TextPart`]. The byte offsets and
+/// line numbers are located in the original stream.
+pub struct SubParts<'a, P: ReadPart> {
+    parent: &'a TextPart,
+    parts: Partitions<Cursor<&'a str>, P>,
+}
+
+impl<'a, P: ReadPart> Iterator for SubParts<'a, P> {
+    type Item = TextPart;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let part = self.parts.next_part()?;
+        // the line range relative to parent
+        let (i, j) = (part.first_line - 1, part.last_line);
+        let positions = self.parent.positions[i..=j].to_vec();
+        let part = TextPart {
+            index: part.index,
+            start: positions[0],
+            end: positions[j - i],
+            first_line: self.parent.first_line + i,
+            last_line: self.parent.first_line + j - 1,
+            text: part.text,
+            positions,
+        };
+        Some(part)
+    }
+}
+
+/// A tree of parts from hierarchical partitioning.
+#[derive(Debug, Clone)]
+pub struct PartTree {
+    /// The part in this level.
+    pub part: TextPart,
+    /// The parts found inside `part`.
+    pub children: Vec<PartTree>,
+}
+
+impl TextPart {
+    /// Returns an iterator over parts inside this part using partitioner
+    /// `p`, without reading from the original stream again.
+    pub fn subparts<P: ReadPart>(&self, p: P) -> SubParts<'_, P> {
+        let parts = TextReader::from_str(&self.text).partitions(p);
+        SubParts { parent: self, parts }
+    }
+
+    /// Partition this part recursively into a tree, using partitioners in
+    /// `levels` from the outermost to the innermost.
+    pub fn into_tree(self, levels: &[&dyn ReadPart]) -> PartTree {
+        let children = match levels.split_first() {
+            Some((p, rest)) => self.subparts(*p).map(|part| part.into_tree(rest)).collect(),
+            None => vec![],
+        };
+        PartTree { part: self, children }
+    }
+}
+// c3a96e1d ends here
+
+// [[file:../parser.note::b84e0c6d][b84e0c6d]]
+use crate::progress::Monitor;
+
+impl<R: BufRead, P> Partitions<R, P> {
+    /// Report parts found and check cancellation with `monitor`. Bytes
+    /// processed are reported by the reader monitored, such as in
+    /// `TextReader::try_from_path_monitored`.
+    pub fn monitor(mut self, monitor: &Monitor) -> Self {
+        self.monitor = Some(monitor.clone());
+        self
+    }
+
+    /// Return true if reading stopped by cancellation.
+    pub fn is_cancelled(&self) -> bool {
+        self.error.is_some() && self.monitor.as_ref().is_some_and(|m| m.is_cancelled())
+    }
+}
+
+impl<R: BufRead, P: ReadPart> Partitions<R, P> {
+    fn monitored_advance(&mut self) -> Option<usize> {
+        if let Some(m) = &self.monitor {
+            if let Err(e) = m.check() {
+                if !self.finished {
+                    self.fail(self.buffered_lines(), e.to_string());
+                }
+                return None;
+            }
+        }
+        let n = self.advance()?;
+        if let Some(m) = &self.monitor {
+            m.add_part();
+        }
+        Some(n)
+    }
+}
+// b84e0c6d ends here
+
+// [[file:../parser.note::5d2b8e3f][5d2b8e3f]]
+/// A region of text skipped due to errors in [`Recovering`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SkippedRegion {
+    /// The absolute position in bytes of the start of skipped text.
+    pub start: u64,
+    /// The absolute position in bytes of the end of skipped text (exclusive).
+    pub end: u64,
+    /// The line number of the first skipped line (1-based).
+    pub first_line: usize,
+    /// The line number of the last skipped line (1-based).
+    pub last_line: usize,
+    /// The error message.
+    pub message: String,
+}
+
+impl<R: BufRead, P> Partitions<R, P> {
+    /// Put lines of `part` from line `j` (0-based) back to the front of
+    /// buffer, which should be taken out just now.
+    fn unread(&mut self, part: &TextPart, j: usize) {
+        let n = part.positions.len() - 1;
+        debug_assert_eq!(part.positions[n], self.positions[0]);
+        let rest: String = part.text.split_inclusive('\n').skip(j).collect();
+        let m = rest.len();
+        let mut offsets = vec![0];
+        offsets.extend(rest.split_inclusive('\n').scan(0, |k, line| {
+            *k += line.len();
+            Some(*k)
+        }));
+        offsets.extend(self.offsets[1..].iter().map(|x| x + m));
+        self.buf.insert_str(0, &rest);
+        self.offsets = offsets;
+        self.positions.splice(0..0, part.positions[j..n].iter().copied());
+        self.nlines -= n - j;
+    }
+
+    /// Drop lines from buffer until a line matching `is_header` found after
+    /// the first line, reading more lines if needed. Return the dropped lines.
+    fn skip_to_header<H: Fn(&str) -> bool>(&mut self, is_header: &H) -> Option<TextPart> {
+        let mut k = 1;
+        loop {
+            if k >= self.buffered_lines() && self.read_n_lines(1).is_none() {
+                break;
+            }
+            if is_header(&self.buf[self.offsets[k]..self.offsets[k + 1]]) {
+                break;
+            }
+            k += 1;
+        }
+        let k = k.min(self.buffered_lines());
+        if k == 0 {
+            return None;
+        }
+        Some(self.take_lines(k))
+    }
+}
+
+/// An iterator over records parsed from parts, which skips parts failed to
+/// parse, and resynchronizes at the next header line.
+pub struct Recovering<R: BufRead, P, H, F> {
+    parts: Partitions<R, P>,
+    is_header: H,
+    parse: F,
+    skipped: Vec<SkippedRegion>,
+}
+
+impl<R, P, H, F, T> Iterator for Recovering<R, P, H, F>
+where
+    R: BufRead,
+    P: ReadPart,
+    H: Fn(&str) -> bool,
+    F: FnMut(&str) -> Result<T>,
+{
+    type Item = T;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let Some(part) = self.parts.next_part() else {
+                // resume from errors in partitioning
+                if self.parts.is_cancelled() {
+                    return None;
+                }
+                let e = self.parts.error.take()?;
+                self.parts.finished = false;
+                let part = self.parts.skip_to_header(&self.is_header)?;
+                self.skip(&part, part.last_line - part.first_line + 1, e.to_string());
+                continue;
+            };
+            match (self.parse)(&part.text) {
+                Ok(x) => return Some(x),
+                Err(e) => {
+                    let nlines = part.last_line + 1 - part.first_line;
+                    let j = part.text.split_inclusive('\n').skip(1).position(|line| (self.is_header)(line));
+                    match j {
+                        Some(j) => {
+                            self.skip(&part, j + 1, format!("{e:#}"));
+                            self.parts.unread(&part, j + 1);
+                        }
+                        None => self.skip(&part, nlines, format!("{e:#}")),
+                    }
+                }
+            }
+        }
+    }
+}
+
+impl<R: BufRead, P, H, F> Recovering<R, P, H, F> {
+    /// Record the first `n` lines in `part` as skipped.
+    fn skip(&mut self, part: &TextPart, n: usize, message: String) {
+        let region = SkippedRegion {
+            start: part.start,
+            end: part.positions[n],
+            first_line: part.first_line,
+            last_line: part.first_line + n - 1,
+            message,
+        };
+        warn!("skipped lines {}-{}: {}", region.first_line, region.last_line, region.message);
+        self.skipped.push(region);
+    }
+
+    /// Return the regions skipped so far.
+    pub fn skipped(&self) -> &[SkippedRegion] {
+        &self.skipped
+    }
+}
+
+impl<R, P, H, F, T> Recovering<R, P, H, F>
+where
+    R: BufRead,
+    P: ReadPart,
+    H: Fn(&str) -> bool,
+    F: FnMut(&str) -> Result<T>,
+{
+    /// Read all remaining records. Return the records and all skipped
+    /// regions.
+    pub fn finish(mut self) -> (Vec<T>, Vec<SkippedRegion>) {
+        let records = self.by_ref().collect();
+        (records, self.skipped)
+    }
+}
+
+impl<R: BufRead, P: ReadPart> Partitions<R, P> {
+    /// Parse each part with `parse`, and skip parts that failed to parse or
+    /// partition instead of stopping. Reading resumes at the next line
+    /// matching `is_header`.
+    pub fn recovering<H, F, T>(self, is_header: H, parse: F) -> Recovering<R, P, H, F>
+    where
+        H: Fn(&str) -> bool,
+        F: FnMut(&str) -> Result<T>,
+    {
+        Recovering {
+            parts: self,
+            is_header,
+            parse,
+            skipped: vec![],
+        }
+    }
+}
+// 5d2b8e3f ends here
+
 // [[file:../parser.note::1970f69f][1970f69f]]
 #[cfg(test)]
 mod test {
@@ -398,6 +1299,340 @@ mod test {
         Ok(())
     }
 
+    #[test]
+    fn test_builtin_partitions() -> Result<()> {
+        let f = "./tests/files/multi.xyz";
+        let reader = TextReader::try_from_path(f.as_ref())?;
+        let parts: Vec<_> = reader.partitions(FixedLines(10)).collect();
+        assert_eq!(parts.len(), 10);
+        assert_eq!(parts[9].lines().count(), 9);
+        let mut parts = TextReader::try_from_path(f.as_ref())?.partitions(FixedLines(0));
+        assert!(parts.next().is_none());
+        assert!(parts.error().is_some());
+
+        let reader = TextReader::try_from_path(f.as_ref())?;
+        let parts: Vec<_> = reader.partitions(CountHeader::xyz()).collect();
+        assert_eq!(parts.len(), 6);
+        assert_eq!(parts[1].lines().count(), 12);
+        assert_eq!(parts[5].lines().count(), 15);
+
+        // count line at line 4
+        let f = "./tests/files/lammps-test.dump";
+        let reader = TextReader::try_from_path(f.as_ref())?;
+        let parts: Vec<_> = reader.partitions(CountHeader { line: 4, extra: 5 }).collect();
+        assert_eq!(parts.len(), 3);
+        assert!(parts.iter().all(|part| part.starts_with("ITEM: TIMESTEP\n")));
+        assert!(parts.iter().all(|part| part.lines().count() == 546));
+
+        let f = "./tests/files/multi.pxyz";
+        let reader = TextReader::try_from_path(f.as_ref())?;
+        let parts: Vec<_> = reader.partitions(BlankSeparated).collect();
+        assert_eq!(parts.len(), 6);
+        assert_eq!(parts[0].lines().count(), 17);
+        assert_eq!(parts[1].lines().count(), 11);
+        // the same parts delimited by blank lines
+        let reader = TextReader::try_from_path(f.as_ref())?;
+        let delimited: Vec<_> = reader.partitions(Delimited(String::new())).collect();
+        assert_eq!(delimited, parts);
+
+        // frame headers in lammps dump file
+        let f = "./tests/files/lammps-test.dump";
+        let reader = TextReader::try_from_path(f.as_ref())?;
+        let p = HeaderFooter(|l: &str| l.starts_with("ITEM: TIMESTEP"), |l: &str| l.starts_with("ITEM: NUMBER OF ATOMS"));
+        let parts: Vec<_> = reader.partitions(p).collect();
+        assert_eq!(parts.len(), 3);
+        assert_eq!(parts[0], "ITEM: TIMESTEP\n0\nITEM: NUMBER OF ATOMS\n");
+        assert!(parts.iter().all(|part| part.lines().count() == 3));
+
+        // SDF records
+        let txt = "mol1\nM  END\n$$$$\nmol2\nM  END\n$$$$ \nmol3\nM  END\n$$$$\n";
+        let parts: Vec<_> = TextReader::from_str(txt).partitions(Delimited("$$$$".into())).collect();
+        assert_eq!(parts.len(), 3);
+        assert_eq!(parts[1], "mol2\nM  END\n$$$$ \n");
+
+        let txt = "junk\nBEGIN\na\nEND\njunk\njunk\nBEGIN\nb\nc\nEND\njunk";
+        let p = HeaderFooter(|l: &str| l.starts_with("BEGIN"), |l: &str| l.starts_with("END"));
+        let parts: Vec<_> = TextReader::from_str(txt).partitions(p).collect();
+        assert_eq!(parts.len(), 2);
+        assert_eq!(parts[1], "BEGIN\nb\nc\nEND\n");
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_partition_combinators() -> Result<()> {
+        let f = "./tests/files/multi.xyz";
+        let reader = TextReader::try_from_path(f.as_ref())?;
+        let parts: Vec<_> = reader.partitions(Repeat::new(CountHeader::xyz(), 2)).collect();
+        assert_eq!(parts.len(), 3);
+        assert_eq!(parts[0].lines().count(), 30);
+        assert_eq!(parts[2].lines().count(), 33);
+
+        let f = "./tests/files/lammps-test.dump";
+        let reader = TextReader::try_from_path(f.as_ref())?;
+        let p = Repeat::new(Preceded(|line: &str| line.starts_with("ITEM: TIMESTEP")), 2);
+        let parts: Vec<_> = reader.partitions(p).collect();
+        assert_eq!(parts.len(), 2);
+        assert_eq!(parts[0].lines().count(), 546 * 2);
+
+        // incomplete last group at EOF does not affect the next use
+        let p = Repeat::new(Preceded(|line: &str| line.starts_with('#')), 2);
+        let parts: Vec<_> = TextReader::from_str("#\na\n#\nb\nc\n").partitions(&p).collect();
+        assert_eq!(parts, ["#\na\n#\nb\nc\n"]);
+        let txt = "#\nx\ny\nz\n#\n1\n#\n2\n";
+        let parts: Vec<_> = TextReader::from_str(txt).partitions(&p).collect();
+        assert_eq!(parts, ["#\nx\ny\nz\n#\n1\n", "#\n2\n"]);
+
+        // skip the first frame
+        let f = "./tests/files/multi.xyz";
+        let reader = TextReader::try_from_path(f.as_ref())?;
+        let parts: Vec<_> = reader.partitions(CountHeader::xyz()).skip_preamble(18).collect();
+        assert_eq!(parts.len(), 5);
+        assert!(parts[0].starts_with("          10\n"));
+
+        let reader = TextReader::try_from_path(f.as_ref())?;
+        let parts = reader.partitions(CountHeader::xyz()).skip_preamble(18).max_parts(2);
+        assert_eq!(parts.count(), 2);
+        let reader = TextReader::try_from_path(f.as_ref())?;
+        let parts = reader.partitions(CountHeader::xyz()).max_parts(0);
+        assert_eq!(parts.count(), 0);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_read_actions() -> Result<()> {
+        // warnings interleaved in xyz frames
+        struct XyzWarned;
+        impl ReadPart for XyzWarned {
+            fn read_next(&self, context: ReadContext) -> ReadAction {
+                if context.line(1).starts_with("WARNING") {
+                    return ReadAction::Skip(1);
+                }
+                if context.line(1).starts_with("END") {
+                    return ReadAction::Stop;
+                }
+                CountHeader::xyz().read_next(context)
+            }
+        }
+
+        let txt = "WARNING: a\n1\ntitle\nH 0 0 0\nWARNING: b\nWARNING: c\n2\ntitle\nH 0 0 0\nH 0 0 1\nEND\n1\n";
+        let parts: Vec<_> = TextReader::from_str(txt).partitions(XyzWarned).collect();
+        assert_eq!(parts.len(), 2);
+        assert_eq!(parts[0], "1\ntitle\nH 0 0 0\n");
+        assert_eq!(parts[1], "2\ntitle\nH 0 0 0\nH 0 0 1\n");
+
+        // typed error with absolute line number
+        let txt = "1\ntitle\nH 0 0 0\n2\ntitle\nH 0 0 0\nH 0 0 1\nx\ntitle\n1\ntitle\nH 0 0 0\n";
+        let mut parts = TextReader::from_str(txt).partitions(CountHeader::xyz());
+        assert_eq!(parts.by_ref().count(), 2);
+        let e = parts.error().unwrap();
+        assert_eq!(e.line, 8);
+        assert!(e.message.contains("invalid count line"));
+        assert!(parts.next().is_none());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_part_metadata() -> Result<()> {
+        let f = "./tests/files/multi.xyz";
+        let reader = TextReader::try_from_path(f.as_ref())?;
+        let parts: Vec<_> = reader.partitions(CountHeader::xyz()).with_metadata().collect();
+        assert_eq!(parts.len(), 6);
+        assert_eq!(parts[0].index, 0);
+        assert_eq!(parts[0].first_line, 1);
+        assert_eq!(parts[0].last_line, 18);
+        assert_eq!(parts[1].first_line, 19);
+        assert_eq!(parts[5].last_line, 99);
+        assert_eq!(parts[5].location(), "part 5 (lines 85-99, bytes 2904..3420)");
+
+        // byte offsets refer to the original CRLF text
+        let txt = gut::fs::read_file(f)?;
+        assert_eq!(parts[5].end as usize, txt.len());
+        for part in parts.iter() {
+            let raw = &txt[part.start as usize..part.end as usize];
+            assert_eq!(raw.replace("\r\n", "\n"), part.text);
+        }
+
+        // skipped lines are counted
+        let txt = "junk\nBEGIN\na\nEND\njunk\njunk\nBEGIN\nb\nc\nEND\njunk";
+        let p = HeaderFooter(|l: &str| l.starts_with("BEGIN"), |l: &str| l.starts_with("END"));
+        let mut parts = TextReader::from_str(txt).partitions(p).skip_preamble(1);
+        let part = parts.next_part().unwrap();
+        assert_eq!((part.first_line, part.last_line), (2, 4));
+        assert_eq!(part.byte_range(), 5..17);
+        let part = parts.next_part().unwrap();
+        assert_eq!((part.index, part.first_line, part.last_line), (1, 7, 10));
+        assert_eq!(&txt[part.start as usize..part.end as usize], part.text);
+
+        let parts = TextReader::from_str("a\nb\n").partitions(FixedLines(1)).starting_at(100, 11);
+        let parts: Vec<_> = parts.with_metadata().collect();
+        assert_eq!((parts[1].start, parts[1].first_line), (102, 12));
+        // too late to set the start
+        let mut parts = TextReader::from_str("a\nb\n").partitions(FixedLines(1));
+        parts.next();
+        let mut parts = parts.starting_at(100, 11);
+        assert!(parts.next().is_none());
+        assert!(parts.error().is_some());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_nested_parts() -> Result<()> {
+        // frames => sections
+        let f = "./tests/files/lammps-test.dump";
+        let txt = gut::fs::read_file(f)?;
+        let frames = Preceded(|line: &str| line.starts_with("ITEM: TIMESTEP"));
+        let sections = Preceded(|line: &str| line.starts_with("ITEM:"));
+        let mut parts = TextReader::try_from_path(f.as_ref())?.partitions(frames).with_metadata();
+        let _ = parts.next();
+        let frame = parts.next().unwrap();
+        assert_eq!(frame.index, 1);
+        let subparts: Vec<_> = frame.subparts(&sections).collect();
+        assert_eq!(subparts.len(), 4);
+        assert_eq!(subparts[0].first_line, frame.first_line);
+        assert_eq!(subparts[1].first_line, frame.first_line + 2);
+        assert!(subparts[3].text.starts_with("ITEM: ATOMS"));
+        assert_eq!(subparts[3].end, frame.end);
+        for part in subparts.iter() {
+            assert_eq!(&txt[part.start as usize..part.end as usize], part.text);
+        }
+
+        // frames => lines => fields, with CRLF line ending
+        let f = "./tests/files/multi.xyz";
+        let txt = gut::fs::read_file(f)?;
+        let levels: [&dyn ReadPart; 2] = [&FixedLines(2), &FixedLines(1)];
+        let trees: Vec<_> = TextReader::try_from_path(f.as_ref())?
+            .partitions(CountHeader::xyz())
+            .with_metadata()
+            .map(|part| part.into_tree(&levels))
+            .collect();
+        assert_eq!(trees.len(), 6);
+        let node = &trees[1].children[1];
+        assert_eq!(node.children.len(), 2);
+        let part = &node.children[1].part;
+        assert_eq!((part.index, part.first_line, part.last_line), (1, 22, 22));
+        let raw = &txt[part.start as usize..part.end as usize];
+        assert_eq!(raw.replace("\r\n", "\n"), part.text);
+        assert!(raw.ends_with("\r\n"));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_partitions_resume() -> Result<()> {
+        let f = "./tests/files/multi.xyz";
+        let all: Vec<_> = TextReader::try_from_path(f.as_ref())?.partitions(CountHeader::xyz()).with_metadata().collect();
+
+        // stopped in the middle of reading with lines pending
+        let p = Preceded(|line: &str| line.trim().parse::<usize>().is_ok());
+        let mut parts = TextReader::try_from_path(f.as_ref())?.partitions(&p);
+        let _ = parts.next_part();
+        let _ = parts.next_text();
+        let cp = parts.checkpoint();
+        assert_eq!(cp.part_index, 2);
+        assert_eq!(cp.pending.lines().count(), 1);
+        let json = serde_json::to_string(&cp)?;
+
+        let cp: Checkpoint = serde_json::from_str(&json)?;
+        let reader = TextReader::try_from_path(f.as_ref())?;
+        let rest: Vec<_> = Partitions::resume(reader, &p, &cp)?.with_metadata().collect();
+        assert_eq!(rest.len(), 4);
+        assert_eq!(rest[..], all[2..]);
+
+        // resume before reading any part
+        let parts = TextReader::try_from_path(f.as_ref())?.partitions(CountHeader::xyz());
+        let cp = parts.checkpoint();
+        let reader = TextReader::try_from_path(f.as_ref())?;
+        assert_eq!(Partitions::resume(reader, CountHeader::xyz(), &cp)?.count(), 6);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_recovering() -> Result<()> {
+        let txt = gut::fs::read_file("./tests/files/multi.xyz")?.replace("\r\n", "\n");
+        let mut lines: Vec<_> = txt.lines().map(|line| line.to_owned()).collect();
+        // overflow in coordinates in the third frame
+        lines[40] = "   C   ********   0.97077  -0.01878".into();
+        // invalid count line in the fifth frame
+        lines[66] = "  **".into();
+        // the first frame truncated
+        lines.drain(14..18);
+        let txt = lines.join("\n") + "\n";
+
+        let parse_xyz = |part: &str| -> Result<usize> {
+            let lines: Vec<_> = part.lines().collect();
+            let n: usize = lines[0].trim().parse()?;
+            ensure!(lines.len() == n + 2, "frame truncated");
+            for line in &lines[2..] {
+                let fields: Vec<_> = line.split_whitespace().collect();
+                ensure!(fields.len() == 4, "invalid atom line: {line}");
+                for x in &fields[1..] {
+                    let _: f64 = x.parse()?;
+                }
+            }
+            Ok(n)
+        };
+        let is_header = |line: &str| line.trim().parse::<usize>().is_ok();
+        let parts = TextReader::from_str(&txt).partitions(CountHeader::xyz());
+        let (records, skipped) = parts.recovering(is_header, parse_xyz).finish();
+        assert_eq!(records, [10, 16, 13]);
+        assert_eq!(skipped.len(), 3);
+        assert_eq!((skipped[0].first_line, skipped[0].last_line), (1, 14));
+        assert_eq!((skipped[1].first_line, skipped[1].last_line), (27, 44));
+        assert_eq!((skipped[2].first_line, skipped[2].last_line), (63, 80));
+        assert!(skipped[2].message.contains("line 63"), "{}", skipped[2].message);
+        for region in skipped {
+            let text = &txt[region.start as usize..region.end as usize];
+            assert_eq!(text.lines().count(), region.last_line - region.first_line + 1);
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_lending_parts() -> Result<()> {
+        let f = "./tests/files/multi.xyz";
+        let parts: Vec<_> = TextReader::try_from_path(f.as_ref())?.partitions(CountHeader::xyz()).collect();
+
+        let mut texts = TextReader::try_from_path(f.as_ref())?.partitions(CountHeader::xyz());
+        let mut i = 0;
+        while let Some(text) = texts.next_text() {
+            assert_eq!(text, parts[i]);
+            i += 1;
+        }
+        assert_eq!(i, 6);
+        // mixed with allocating reads
+        let mut texts = TextReader::try_from_path(f.as_ref())?.partitions(CountHeader::xyz());
+        assert_eq!(texts.next_text(), Some(parts[0].as_str()));
+        let part = texts.next_part().unwrap();
+        assert_eq!((part.index, part.first_line), (1, 19));
+        assert_eq!(texts.next(), Some(parts[2].clone()));
+
+        let mut n = 0;
+        let p = Preceded(|line: &str| line.trim().parse::<usize>().is_ok());
+        TextReader::try_from_path(f.as_ref())?.partitions(p).for_each_text(|text| {
+            assert_eq!(text, parts[n]);
+            n += 1;
+        });
+        assert_eq!(n, 6);
+
+        let chunks: Vec<_> = TextReader::try_from_path(f.as_ref())?.chunks(10).collect();
+        let mut reader = TextReader::try_from_path(f.as_ref())?;
+        let mut i = 0;
+        let n = reader.for_each_chunk(10, |chunk| {
+            assert_eq!(chunk, chunks[i]);
+            i += 1;
+            Ok(())
+        })?;
+        assert_eq!(n, 10);
+
+        Ok(())
+    }
+
     // test default impl
     #[test]
     fn test_read_part_default() -> Result<()> {
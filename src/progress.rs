@@ -0,0 +1,337 @@
+// [[file:../parser.note::*docs][docs:1]]
+//! Progress reporting and cancellation for long running scans.
+//!
+//! # Example
+//!
+//! ```
+//! use gchemol_parser::progress::{is_cancelled, CancelToken, Monitor};
+//! use gchemol_parser::TextReader;
+//!
+//! let token = CancelToken::new();
+//! let monitor = Monitor::new().observer(|p| println!("{} bytes read", p.bytes)).cancel_token(&token);
+//! let f = "./tests/files/lammps-test.dump";
+//! let mut reader = TextReader::try_from_path_monitored(f.as_ref(), &monitor).unwrap();
+//!
+//! // stop from another thread, or in Ctrl-C handler
+//! token.cancel();
+//! let mut s = String::new();
+//! let e = reader.read_to_string(&mut s).unwrap_err();
+//! assert!(is_cancelled(&e));
+//! ```
+// docs:1 ends here
+
+// [[file:../parser.note::9e5c1b7a][9e5c1b7a]]
+use crate::reader::TextReader;
+
+use gut::fs::*;
+use gut::prelude::*;
+
+use std::io::SeekFrom;
+use std::sync::atomic::{AtomicBool, AtomicU64, AtomicUsize, Ordering};
+use std::sync::Arc;
+// 9e5c1b7a ends here
+
+// [[file:../parser.note::1b4f8d2c][1b4f8d2c]]
+/// The error for operations stopped by [`CancelToken`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Cancelled;
+
+impl std::fmt::Display for Cancelled {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "operation cancelled")
+    }
+}
+
+impl std::error::Error for Cancelled {}
+
+impl From<Cancelled> for std::io::Error {
+    fn from(e: Cancelled) -> Self {
+        std::io::Error::other(e)
+    }
+}
+
+/// Return true if error `e` is caused by cancellation.
+pub fn is_cancelled(e: &Error) -> bool {
+    e.chain().any(|c| {
+        c.is::<Cancelled>()
+            || c.downcast_ref::<std::io::Error>()
+                .and_then(|e| e.get_ref())
+                .is_some_and(|e| e.is::<Cancelled>())
+    })
+}
+
+/// A token for cancelling scans, which can be shared across threads.
+#[derive(Debug, Clone, Default)]
+pub struct CancelToken(Arc<AtomicBool>);
+
+impl CancelToken {
+    /// Create a new token not cancelled.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Request cancellation.
+    pub fn cancel(&self) {
+        self.0.store(true, Ordering::Relaxed);
+    }
+
+    /// Return true if cancellation is requested.
+    pub fn is_cancelled(&self) -> bool {
+        self.0.load(Ordering::Relaxed)
+    }
+}
+
+/// The progress of a scan.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Progress {
+    /// The number of bytes processed.
+    pub bytes: u64,
+    /// The total number of bytes if known.
+    pub total: Option<u64>,
+    /// The number of parts found.
+    pub parts: usize,
+}
+
+#[derive(Debug, Default)]
+struct State {
+    bytes: AtomicU64,
+    // zero for unknown
+    total: AtomicU64,
+    parts: AtomicUsize,
+    // the bytes processed in last report
+    reported: AtomicU64,
+}
+
+type Observer = Arc<dyn Fn(&Progress) + Send + Sync>;
+
+/// Report progress to an observer and check cancellation during scans. It
+/// is cheap to clone, and clones share the same state.
+#[derive(Clone)]
+pub struct Monitor {
+    observer: Option<Observer>,
+    token: CancelToken,
+    // report progress after processing this number of bytes
+    interval: u64,
+    state: Arc<State>,
+}
+
+impl std::fmt::Debug for Monitor {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        f.debug_struct("Monitor")
+            .field("token", &self.token)
+            .field("interval", &self.interval)
+            .field("progress", &self.progress())
+            .finish()
+    }
+}
+
+impl Default for Monitor {
+    fn default() -> Self {
+        Self {
+            observer: None,
+            token: CancelToken::new(),
+            interval: 1 << 20,
+            state: Arc::default(),
+        }
+    }
+}
+
+impl Monitor {
+    /// Create a monitor without observer.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Call `f` with current progress periodically.
+    pub fn observer<F: Fn(&Progress) + Send + Sync + 'static>(mut self, f: F) -> Self {
+        self.observer = Some(Arc::new(f));
+        self
+    }
+
+    /// Stop scans when `token` is cancelled.
+    pub fn cancel_token(mut self, token: &CancelToken) -> Self {
+        self.token = token.clone();
+        self
+    }
+
+    /// Report progress after processing every `n` bytes. The default is 1
+    /// MiB.
+    pub fn interval(mut self, n: u64) -> Self {
+        self.interval = n.max(1);
+        self
+    }
+
+    /// Return current progress.
+    pub fn progress(&self) -> Progress {
+        let total = self.state.total.load(Ordering::Relaxed);
+        Progress {
+            bytes: self.state.bytes.load(Ordering::Relaxed),
+            total: (total > 0).then_some(total),
+            parts: self.state.parts.load(Ordering::Relaxed),
+        }
+    }
+
+    /// Return true if cancellation is requested.
+    pub fn is_cancelled(&self) -> bool {
+        self.token.is_cancelled()
+    }
+
+    /// Return `Cancelled` error if cancellation is requested.
+    pub fn check(&self) -> std::result::Result<(), Cancelled> {
+        if self.is_cancelled() {
+            Err(Cancelled)
+        } else {
+            Ok(())
+        }
+    }
+
+    pub(crate) fn set_total(&self, total: Option<u64>) {
+        self.state.total.store(total.unwrap_or(0), Ordering::Relaxed);
+    }
+
+    pub(crate) fn set_bytes(&self, bytes: u64) {
+        self.state.bytes.store(bytes, Ordering::Relaxed);
+        let reported = self.state.reported.load(Ordering::Relaxed);
+        if bytes.abs_diff(reported) >= self.interval {
+            self.report();
+        }
+    }
+
+    pub(crate) fn add_part(&self) {
+        self.state.parts.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Report current progress to observer.
+    pub(crate) fn report(&self) {
+        let progress = self.progress();
+        self.state.reported.store(progress.bytes, Ordering::Relaxed);
+        if let Some(f) = &self.observer {
+            f(&progress);
+        }
+    }
+}
+// 1b4f8d2c ends here
+
+// [[file:../parser.note::6a0d3e85][6a0d3e85]]
+/// A reader reporting bytes read to [`Monitor`], and failing with
+/// [`Cancelled`] error on cancellation.
+#[derive(Debug)]
+pub struct Monitored<R> {
+    inner: R,
+    pos: u64,
+    monitor: Monitor,
+}
+
+impl<R> Monitored<R> {
+    /// Monitor reading from `inner`.
+    pub fn new(inner: R, monitor: &Monitor) -> Self {
+        Self {
+            inner,
+            pos: 0,
+            monitor: monitor.clone(),
+        }
+    }
+}
+
+impl<R: Read> Read for Monitored<R> {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        self.monitor.check()?;
+        let n = self.inner.read(buf)?;
+        self.pos += n as u64;
+        self.monitor.set_bytes(self.pos);
+        if n == 0 {
+            self.monitor.report();
+        }
+        Ok(n)
+    }
+}
+
+impl<R: Seek> Seek for Monitored<R> {
+    fn seek(&mut self, pos: SeekFrom) -> std::io::Result<u64> {
+        self.pos = self.inner.seek(pos)?;
+        self.monitor.set_bytes(self.pos);
+        Ok(self.pos)
+    }
+}
+
+impl TextReader<BufReader<Monitored<File>>> {
+    /// Build a text reader for file from path `p`, which reports progress
+    /// and checks cancellation with `monitor`.
+    pub fn try_from_path_monitored(p: &Path, monitor: &Monitor) -> Result<Self> {
+        let f = File::open(p).with_context(|| format!("Failed to open file {:?}", p))?;
+        monitor.set_total(Some(f.metadata()?.len()));
+        Ok(TextReader::new(Monitored::new(f, monitor)))
+    }
+}
+// 6a0d3e85 ends here
+
+// [[file:../parser.note::d47a2c19][d47a2c19]]
+#[test]
+fn test_progress() -> Result<()> {
+    use crate::partition::Preceded;
+    use crate::GrepReader;
+    use std::sync::Mutex;
+
+    let f = "./tests/files/lammps-test.dump";
+    let len = std::fs::metadata(f)?.len();
+    let reports = Arc::new(Mutex::new(vec![]));
+    let r = reports.clone();
+    let monitor = Monitor::new().interval(1000).observer(move |p| r.lock().unwrap().push(*p));
+
+    // read to string
+    let mut reader = TextReader::try_from_path_monitored(f.as_ref(), &monitor)?;
+    let mut s = String::new();
+    reader.read_to_string(&mut s)?;
+    let last = *reports.lock().unwrap().last().unwrap();
+    assert_eq!(last.bytes, len);
+    assert_eq!(last.total, Some(len));
+
+    // seek line
+    let monitor = Monitor::new();
+    let mut reader = TextReader::try_from_path_monitored(f.as_ref(), &monitor)?;
+    let n = reader.seek_line(|line| line.starts_with("ITEM: ATOMS"))?;
+    assert!(monitor.progress().bytes >= n as u64);
+
+    // partitions
+    let p = Preceded(|line: &str| line.starts_with("ITEM: TIMESTEP"));
+    let reader = TextReader::try_from_path_monitored(f.as_ref(), &monitor)?;
+    let mut parts = reader.partitions(p).monitor(&monitor);
+    assert!(parts.next().is_some());
+    assert_eq!(monitor.progress().parts, 1);
+    assert_eq!(parts.count(), 2);
+    assert_eq!(monitor.progress().parts, 3);
+
+    // cancelled in the middle
+    let token = CancelToken::new();
+    let monitor = Monitor::new().cancel_token(&token);
+    let p = Preceded(|line: &str| line.starts_with("ITEM: TIMESTEP"));
+    let reader = TextReader::try_from_path_monitored(f.as_ref(), &monitor)?;
+    let mut parts = reader.partitions(p).monitor(&monitor);
+    assert!(parts.next().is_some());
+    token.cancel();
+    assert!(parts.next().is_none());
+    assert!(parts.is_cancelled());
+    assert!(parts.error().is_some());
+
+    let mut reader = TextReader::try_from_path_monitored(f.as_ref(), &monitor)?;
+    let e = reader.seek_line(|line| line.starts_with("ITEM: ATOMS")).unwrap_err();
+    assert!(is_cancelled(&e));
+    let mut s = String::new();
+    let e = reader.read_line(&mut s).unwrap_err();
+    assert!(is_cancelled(&e));
+    assert!(!is_cancelled(&anyhow!("other error")));
+
+    // grep
+    let mut reader = GrepReader::try_from_path(f.as_ref())?;
+    reader.monitor(&monitor);
+    let e = reader.mark("^ITEM: TIMESTEP", None).unwrap_err();
+    assert!(is_cancelled(&e));
+    let monitor = Monitor::new();
+    reader.monitor(&monitor);
+    assert_eq!(reader.mark("^ITEM: TIMESTEP", None)?, 3);
+    let progress = monitor.progress();
+    assert_eq!((progress.bytes, progress.total, progress.parts), (len, Some(len), 3));
+
+    Ok(())
+}
+// d47a2c19 ends here
@@ -2,6 +2,8 @@
 use gut::fs::*;
 use gut::prelude::*;
 
+use crate::limits::Limits;
+use crate::newline::Newline;
 use std::io::Cursor;
 // 8e7e8fdf ends here
 
@@ -21,13 +23,20 @@ fn text_file_reader<P: AsRef<Path>>(p: P) -> Result<FileReader> {
 /// A stream reader for large text file
 pub struct TextReader<R> {
     inner: R,
+    pub(crate) limits: Limits,
+    // line ending policy, or None for the default
+    pub(crate) newline: Option<Newline>,
 }
 
 impl TextReader<FileReader> {
     /// Build a text reader for file from path `p`.
     pub fn try_from_path(p: &Path) -> Result<Self> {
         let reader = text_file_reader(p)?;
-        let parser = Self { inner: reader };
+        let parser = Self {
+            inner: reader,
+            limits: Limits::default(),
+            newline: None,
+        };
         Ok(parser)
     }
 }
@@ -36,14 +45,22 @@ impl<'a> TextReader<Cursor<&'a str>> {
     /// Build a text reader for string slice.
     pub fn from_str(s: &'a str) -> Self {
         let r = Cursor::new(s);
-        TextReader { inner: r }
+        TextReader {
+            inner: r,
+            limits: Limits::default(),
+            newline: None,
+        }
     }
 }
 
 impl<R: Read> TextReader<BufReader<R>> {
     /// Build a text reader from a struct implementing Read trait.
     pub fn new(r: R) -> Self {
-        Self { inner: BufReader::new(r) }
+        Self {
+            inner: BufReader::new(r),
+            limits: Limits::default(),
+            newline: None,
+        }
     }
 }
 
@@ -53,22 +70,131 @@ impl<R: BufRead> TextReader<R> {
     /// # NOTE
     /// - This function will return the total number of bytes read.
     /// - If this function returns Ok(0), the stream has reached EOF.
+    /// - A typed `LimitError` is returned if any limit set in
+    ///   [`TextReader::with_limits`] exceeded.
+    /// - Line ending is handled according to the policy set in
+    ///   [`TextReader::with_newline`]. The returned number of bytes is
+    ///   counted before conversion.
     pub fn read_line(&mut self, buf: &mut String) -> Result<usize> {
-        self.inner.read_line(buf).map_err(|e| anyhow!("Read line failure"))
+        let newline = self.newline();
+        if self.limits.is_unlimited() {
+            newline.read_line(&mut self.inner, buf)
+        } else {
+            let start = buf.len();
+            let n = self.read_line_limited(buf)?;
+            newline.normalize_from(buf, start);
+            Ok(n)
+        }
+    }
+
+    /// Return the policy for handling line endings.
+    pub(crate) fn newline(&self) -> Newline {
+        self.newline.unwrap_or_default()
+    }
+
+    /// Read a new line within limits without reading the whole line into
+    /// memory first.
+    fn read_line_limited(&mut self, buf: &mut String) -> Result<usize> {
+        let newline = self.newline();
+        let mut line = vec![];
+        loop {
+            let available = self.inner.fill_buf().context("Read line failure")?;
+            if available.is_empty() {
+                break;
+            }
+            let (chunk, done) = match available.iter().position(|&b| newline.is_eol(b)) {
+                Some(i) => (&available[..=i], true),
+                None => (available, false),
+            };
+            self.limits.check_binary(chunk)?;
+            // a single line could not be larger than the buffer
+            self.limits.check_line_length(line.len() + chunk.len())?;
+            self.limits.check_buffered(line.len() + chunk.len())?;
+            line.extend_from_slice(chunk);
+            let n = chunk.len();
+            self.inner.consume(n);
+            if done {
+                break;
+            }
+        }
+        // a single CR may be followed by LF
+        if newline == Newline::Universal && line.ends_with(b"\r") && self.inner.fill_buf()?.first() == Some(&b'\n') {
+            self.inner.consume(1);
+            line.push(b'\n');
+        }
+        let s = std::str::from_utf8(&line).context("stream did not contain valid UTF-8")?;
+        buf.push_str(s);
+        Ok(line.len())
+    }
+
+    /// Set limits on resources used in reading.
+    pub fn with_limits(mut self, limits: Limits) -> Self {
+        self.limits = limits;
+        self
+    }
+
+    /// Set the policy for handling line endings. Line endings are preserved
+    /// by default, except in `Partitions`, where CRLF is converted to LF by
+    /// default.
+    pub fn with_newline(mut self, newline: Newline) -> Self {
+        self.newline = Some(newline);
+        self
     }
 
     /// Returns an iterator over the lines of this reader. Each string returned
     /// will not have a line ending.
     pub fn lines(self) -> impl Iterator<Item = String> {
+        let universal = self.newline() == Newline::Universal;
         // silently ignore UTF-8 error
-        self.inner.lines().filter_map(|s| if let Ok(line) = s { Some(line) } else { None })
+        self.inner.lines().filter_map(|s| if let Ok(line) = s { Some(line) } else { None }).flat_map(move |line| {
+            if universal {
+                // a line may contain more lines ending with a single CR
+                let s = line.strip_suffix('\r').unwrap_or(&line);
+                s.split('\r').map(|s| s.to_owned()).collect()
+            } else {
+                vec![line]
+            }
+        })
+    }
+
+    /// Call `f` on each line without line ending, reusing the same buffer
+    /// without allocation. Return the number of lines read.
+    pub fn for_each_line<F>(&mut self, mut f: F) -> Result<usize>
+    where
+        F: FnMut(&str) -> Result<()>,
+    {
+        let mut line = String::new();
+        let mut n = 0;
+        loop {
+            line.clear();
+            if self.read_line(&mut line)? == 0 {
+                return Ok(n);
+            }
+            let s = line.strip_suffix('\n').unwrap_or(&line);
+            f(s.strip_suffix('\r').unwrap_or(s))?;
+            n += 1;
+        }
     }
 
     /// Read all text into string `buf` (Note: out of memory issue for large
     /// file)
     pub fn read_to_string(&mut self, buf: &mut String) -> Result<usize> {
-        let n = self.inner.read_to_string(buf)?;
-        Ok(n)
+        if self.limits.is_unlimited() {
+            let start = buf.len();
+            let n = self.inner.read_to_string(buf)?;
+            self.newline().normalize_from(buf, start);
+            Ok(n)
+        } else {
+            let mut n = 0;
+            loop {
+                let m = self.read_line(buf)?;
+                if m == 0 {
+                    return Ok(n);
+                }
+                self.limits.check_buffered(buf.len())?;
+                n += m;
+            }
+        }
     }
 }
 // 3f27d680 ends here
@@ -80,11 +206,11 @@ impl<R: BufRead + Seek> TextReader<R> {
     /// Peek next line without moving cursor.
     pub fn peek_line(&mut self) -> Option<String> {
         let mut buf = String::new();
-        match self.inner.read_line(&mut buf) {
+        match self.read_line(&mut buf) {
             Err(_) => None,
             Ok(0) => None,
             Ok(n) => {
-                self.goto_relative(-1 * n as i64).expect("peek line go back");
+                self.goto_relative(-1 * n as i64).ok()?;
                 Some(buf)
             }
         }
@@ -100,7 +226,7 @@ impl<R: BufRead + Seek> TextReader<R> {
         let mut line = String::new();
         let mut m = 0;
         loop {
-            let n = self.inner.read_line(&mut line)?;
+            let n = self.read_line(&mut line)?;
             if n == 0 {
                 // EOF
                 bail!("no matched line found!");
@@ -129,7 +255,7 @@ impl<R: BufRead + Seek> TextReader<R> {
     {
         let mut m = buf.len();
         loop {
-            let n = self.inner.read_line(buf)?;
+            let n = self.read_line(buf)?;
             if n == 0 {
                 // EOF
                 bail!("no matched line found!");
@@ -179,6 +305,163 @@ impl<R: BufRead + Seek> TextReader<R> {
 }
 // 95fe0e8a ends here
 
+// [[file:../parser.note::5c0e7a21][5c0e7a21]]
+impl<R: BufRead + Seek> TextReader<R> {
+    /// Find the position of the first header line starting at or after `pos`
+    /// and before `end`. Return None if no header line found in this range.
+    fn next_header_within<F>(&mut self, pos: u64, end: u64, is_header: &mut F) -> Result<Option<u64>>
+    where
+        F: FnMut(&str) -> bool,
+    {
+        // resync to the start of next line, unless `pos` is already at a line
+        // start. Read in bytes as we could land inside a multibyte char.
+        if pos > 0 {
+            self.goto(pos - 1)?;
+            self.skip_line_bytes()?;
+        } else {
+            self.goto(0)?;
+        }
+
+        let mut line = String::new();
+        loop {
+            let cur = self.get_current_position()?;
+            if cur >= end {
+                return Ok(None);
+            }
+            line.clear();
+            if self.read_line(&mut line)? == 0 {
+                return Ok(None);
+            }
+            if is_header(&line) {
+                return Ok(Some(cur));
+            }
+        }
+    }
+
+    /// Skip bytes until the end of current line.
+    fn skip_line_bytes(&mut self) -> Result<()> {
+        let newline = self.newline();
+        loop {
+            let available = self.inner.fill_buf()?;
+            match available.iter().position(|&b| newline.is_eol(b)) {
+                Some(i) => {
+                    let cr = available[i] == b'\r';
+                    self.inner.consume(i + 1);
+                    if cr && self.inner.fill_buf()?.first() == Some(&b'\n') {
+                        self.inner.consume(1);
+                    }
+                    return Ok(());
+                }
+                None if available.is_empty() => return Ok(()),
+                None => {
+                    let n = available.len();
+                    self.inner.consume(n);
+                }
+            }
+        }
+    }
+
+    /// Seek to the frame with `key` by bisecting the stream in byte offset,
+    /// which is suitable for one-off lookup in a very large file. Return the
+    /// position of the header line of the found frame, and the cursor will be
+    /// moved to there.
+    ///
+    /// # Parameters
+    /// * key: the key of frame to look for. The frames in stream should be
+    ///   sorted by key in ascending order.
+    /// * is_header: returns true if a line is the header line of a frame.
+    /// * parse_key: parse key of a frame, with the cursor located at its header line.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use gchemol_parser::TextReader;
+    ///
+    /// let f = "./tests/files/lammps-test.dump";
+    /// let mut reader = TextReader::try_from_path(f.as_ref()).unwrap();
+    /// let pos = reader
+    ///     .bisect_frame(
+    ///         &100,
+    ///         |line| line.starts_with("ITEM: TIMESTEP"),
+    ///         |r| {
+    ///             let mut s = String::new();
+    ///             r.read_line(&mut s)?;
+    ///             s.clear();
+    ///             r.read_line(&mut s)?;
+    ///             Ok(s.trim().parse::<usize>()?)
+    ///         },
+    ///     )
+    ///     .unwrap();
+    /// assert_eq!(reader.peek_line().unwrap(), "ITEM: TIMESTEP\n");
+    /// ```
+    pub fn bisect_frame<K, F, G>(&mut self, key: &K, mut is_header: F, mut parse_key: G) -> Result<u64>
+    where
+        K: Ord,
+        F: FnMut(&str) -> bool,
+        G: FnMut(&mut Self) -> Result<K>,
+    {
+        // the header of the frame we look for lies in lo..hi
+        let mut lo = 0;
+        let mut hi = self.inner.seek(SeekFrom::End(0))?;
+        while lo < hi {
+            let mid = lo + (hi - lo) / 2;
+            match self.next_header_within(mid, hi, &mut is_header)? {
+                // no header in mid..hi, which includes the case reaching EOF
+                None => hi = mid,
+                Some(pos) => {
+                    self.goto(pos)?;
+                    let k = parse_key(self).with_context(|| format!("failed to parse frame key at {pos}"))?;
+                    match k.cmp(key) {
+                        std::cmp::Ordering::Equal => {
+                            self.goto(pos)?;
+                            return Ok(pos);
+                        }
+                        std::cmp::Ordering::Less => lo = pos + 1,
+                        // no header in mid..pos
+                        std::cmp::Ordering::Greater => hi = mid,
+                    }
+                }
+            }
+        }
+
+        bail!("no frame found with the key");
+    }
+}
+// 5c0e7a21 ends here
+
+// [[file:../parser.note::a85c3e07][a85c3e07]]
+/// The cursor state of a stream reader, which could be saved and used to
+/// resume reading in a new reader exactly from where it stopped.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Checkpoint {
+    /// The byte offset in the stream where reading resumes.
+    pub position: u64,
+    /// The number of lines read before `position`, if it is tracked.
+    pub line: Option<usize>,
+    /// The index of the next part to read.
+    pub part_index: usize,
+    /// The text read in but not consumed yet.
+    pub pending: String,
+    /// The byte offsets of each line in `pending`.
+    pub pending_positions: Vec<u64>,
+}
+
+impl<R: BufRead + Seek> TextReader<R> {
+    /// Return the cursor state for resuming. The line number is not
+    /// tracked by `TextReader`.
+    pub fn checkpoint(&mut self) -> Result<Checkpoint> {
+        let position = self.get_current_position()?;
+        Ok(Checkpoint { position, ..Default::default() })
+    }
+
+    /// Resume reading from the state in `checkpoint`.
+    pub fn resume(&mut self, checkpoint: &Checkpoint) -> Result<()> {
+        ensure!(checkpoint.pending.is_empty(), "cannot resume pending text in TextReader");
+        self.goto(checkpoint.position)
+    }
+}
+// a85c3e07 ends here
+
 // [[file:../parser.note::b7e82299][b7e82299]]
 #[test]
 fn test_reader() -> Result<()> {
@@ -211,6 +494,62 @@ fn test_reader() -> Result<()> {
     reader.read_line(&mut buf);
     assert_eq!(buf, "here\r\n");
 
+    // test lines without allocation
+    let f = "./tests/files/multi.xyz";
+    let mut reader = TextReader::try_from_path(f.as_ref())?;
+    let lines: Vec<_> = TextReader::try_from_path(f.as_ref())?.lines().collect();
+    let mut i = 0;
+    let n = reader.for_each_line(|line| {
+        assert_eq!(line, lines[i]);
+        i += 1;
+        Ok(())
+    })?;
+    assert_eq!(n, 99);
+
     Ok(())
 }
 // b7e82299 ends here
+
+// [[file:../parser.note::7d3b9e40][7d3b9e40]]
+#[test]
+fn test_reader_bisect() -> Result<()> {
+    // frames sorted by timestep
+    let f = "./tests/files/lammps-test.dump";
+    let mut reader = TextReader::try_from_path(f.as_ref())?;
+    let parse_timestep = |r: &mut TextReader<_>| {
+        let mut s = String::new();
+        r.read_line(&mut s)?;
+        s.clear();
+        r.read_line(&mut s)?;
+        Ok(s.trim().parse::<usize>()?)
+    };
+    let is_header = |line: &str| line.starts_with("ITEM: TIMESTEP");
+    for k in [0, 100, 200] {
+        let pos = reader.bisect_frame(&k, is_header, parse_timestep)?;
+        assert_eq!(reader.get_current_position()?, pos);
+        let line = reader.lines().nth(1).unwrap();
+        assert_eq!(line, k.to_string());
+        reader = TextReader::try_from_path(f.as_ref())?;
+    }
+    assert!(reader.bisect_frame(&150, is_header, parse_timestep).is_err());
+    assert!(reader.bisect_frame(&300, is_header, parse_timestep).is_err());
+
+    // header line with key, in CRLF line endings
+    let f = "./tests/files/multi.xyz";
+    let mut reader = TextReader::try_from_path(f.as_ref())?;
+    let parse_conf = |r: &mut TextReader<_>| {
+        let mut s = String::new();
+        r.read_line(&mut s)?;
+        Ok(s.split_whitespace().last().unwrap().parse::<usize>()?)
+    };
+    let is_header = |line: &str| line.starts_with(" Configuration number");
+    for k in [7, 14, 21, 28, 35, 42] {
+        let _ = reader.bisect_frame(&k, is_header, parse_conf)?;
+        let line = reader.peek_line().unwrap();
+        assert!(line.trim_end().ends_with(&format!(" {k}")));
+    }
+    assert!(reader.bisect_frame(&8, is_header, parse_conf).is_err());
+
+    Ok(())
+}
+// 7d3b9e40 ends here
@@ -0,0 +1,389 @@
+// [[file:../parser.note::*docs][docs:1]]
+//! Split a large text file into multiple files, one for each part.
+//!
+//! # Example
+//!
+//! ```
+//! use gchemol_parser::GrepReader;
+//! use gchemol_parser::split::SplitOptions;
+//!
+//! let dir = gut::fs::tempfile::tempdir().unwrap();
+//! let template = dir.path().join("frame_{index:05}.xyz");
+//! let opts = SplitOptions::new(template.to_str().unwrap());
+//!
+//! let mut reader = GrepReader::try_from_path("./tests/files/multi.xyz".as_ref()).unwrap();
+//! reader.mark(r"^\s*\d+\s*$", None).unwrap();
+//! let files = reader.split_frames(&opts, |_| None).unwrap();
+//! assert_eq!(files.len(), 6);
+//! assert!(files[0].ends_with("frame_00000.xyz"));
+//! ```
+// docs:1 ends here
+
+// [[file:../parser.note::a0c9d6e2][a0c9d6e2]]
+use gut::fs::*;
+use gut::prelude::*;
+
+use flate2::write::GzEncoder;
+use std::collections::HashSet;
+// a0c9d6e2 ends here
+
+// [[file:../parser.note::3f1b8e57][3f1b8e57]]
+/// Options for writing parts of text into separate files.
+#[derive(Debug, Clone)]
+pub struct SplitOptions {
+    template: String,
+    gzip: bool,
+    max_open_files: usize,
+    atomic: bool,
+}
+
+impl SplitOptions {
+    /// Write parts into files named from `template`, in which `{index}` is
+    /// replaced with the part index (0-based), and `{key}` is replaced with
+    /// the key of the part. A field can be padded to a fixed width, with
+    /// zeros as in `{index:05}` or with spaces as in `{key:8}`.
+    pub fn new(template: &str) -> Self {
+        Self {
+            template: template.to_owned(),
+            gzip: false,
+            max_open_files: 64,
+            atomic: true,
+        }
+    }
+
+    /// Compress written files in gzip format. The ".gz" suffix will be added
+    /// if missing in file name.
+    pub fn gzip(mut self, gzip: bool) -> Self {
+        self.gzip = gzip;
+        self
+    }
+
+    /// Set the maximum number of files kept open for writing at the same
+    /// time, which matters when many parts are written into the same file
+    /// by `{key}`.
+    pub fn max_open_files(mut self, n: usize) -> Self {
+        self.max_open_files = n.max(1);
+        self
+    }
+
+    /// Write into a temporary file first, and rename it into the final
+    /// destination only when all parts written successfully. This is the
+    /// default.
+    pub fn atomic(mut self, atomic: bool) -> Self {
+        self.atomic = atomic;
+        self
+    }
+
+    /// Return the path of the file for writing part numbered as `index` with
+    /// `key`.
+    pub fn file_path(&self, index: usize, key: Option<&str>) -> Result<PathBuf> {
+        let mut name = render_template(&self.template, index, key)?;
+        if self.gzip && !name.ends_with(".gz") {
+            name.push_str(".gz");
+        }
+        Ok(name.into())
+    }
+
+    fn need_key(&self) -> bool {
+        self.template.contains("{key")
+    }
+}
+
+/// Fill `template` with part `index` and `key`.
+fn render_template(template: &str, index: usize, key: Option<&str>) -> Result<String> {
+    let mut out = String::new();
+    let mut rest = template;
+    while let Some(i) = rest.find('{') {
+        out.push_str(&rest[..i]);
+        let j = rest[i..].find('}').with_context(|| format!("unclosed field in template: {template:?}"))? + i;
+        let field = &rest[i + 1..j];
+        let (name, spec) = field.split_once(':').unwrap_or((field, ""));
+        let value = match name {
+            "index" => index.to_string(),
+            "key" => key.with_context(|| format!("no key found for part {index}"))?.to_owned(),
+            _ => bail!("invalid field {name:?} in template: {template:?}"),
+        };
+        let width: usize = if spec.is_empty() {
+            0
+        } else {
+            spec.parse().with_context(|| format!("invalid width {spec:?} in template: {template:?}"))?
+        };
+        let pad = if spec.starts_with('0') { '0' } else { ' ' };
+        for _ in value.chars().count()..width {
+            out.push(pad);
+        }
+        out.push_str(&value);
+        rest = &rest[j + 1..];
+    }
+    out.push_str(rest);
+    Ok(out)
+}
+// 3f1b8e57 ends here
+
+// [[file:../parser.note::c85d2f19][c85d2f19]]
+/// A file opened for writing a part.
+enum PartSink {
+    Plain(BufWriter<File>),
+    Gzip(GzEncoder<BufWriter<File>>),
+}
+
+impl PartSink {
+    fn open(path: &Path, append: bool, gzip: bool) -> Result<Self> {
+        if let Some(dir) = path.parent() {
+            if !dir.as_os_str().is_empty() {
+                std::fs::create_dir_all(dir)?;
+            }
+        }
+        let f = std::fs::OpenOptions::new()
+            .create(true)
+            .write(true)
+            .append(append)
+            .truncate(!append)
+            .open(path)
+            .with_context(|| format!("Failed to open file {:?}", path))?;
+        if gzip {
+            // appending to a gzip file makes a valid multi-member gzip file
+            let w = GzEncoder::new(BufWriter::new(f), flate2::Compression::default());
+            Ok(Self::Gzip(w))
+        } else {
+            Ok(Self::Plain(BufWriter::new(f)))
+        }
+    }
+
+    fn writer(&mut self) -> &mut dyn Write {
+        match self {
+            Self::Plain(w) => w,
+            Self::Gzip(w) => w,
+        }
+    }
+
+    fn close(self) -> Result<()> {
+        match self {
+            Self::Plain(mut w) => w.flush()?,
+            Self::Gzip(w) => w.finish()?.flush()?,
+        }
+        Ok(())
+    }
+}
+
+/// Write parts of text into separate files.
+pub struct PartFiles {
+    opts: SplitOptions,
+    // files opened for writing, in opening order
+    opened: Vec<(PathBuf, PartSink)>,
+    // all files written, in creation order
+    written: Vec<PathBuf>,
+    seen: HashSet<PathBuf>,
+    finished: bool,
+}
+
+impl PartFiles {
+    /// Write parts into files using options in `opts`.
+    pub fn new(opts: &SplitOptions) -> Self {
+        Self {
+            opts: opts.clone(),
+            opened: vec![],
+            written: vec![],
+            seen: HashSet::new(),
+            finished: false,
+        }
+    }
+
+    /// The path to write into before renaming to `path` in atomic mode.
+    fn staging_path(&self, path: &Path) -> PathBuf {
+        if self.opts.atomic {
+            let name = path.file_name().map(|s| s.to_string_lossy()).unwrap_or_default();
+            path.with_file_name(format!(".{name}.tmp"))
+        } else {
+            path.to_owned()
+        }
+    }
+
+    /// Return writer for file in `path`, reusing opened file if possible.
+    fn writer_for(&mut self, path: &Path) -> Result<&mut dyn Write> {
+        let i = match self.opened.iter().position(|(p, _)| p == path) {
+            Some(i) => i,
+            None => {
+                // close the oldest file if too many opened
+                if self.opened.len() >= self.opts.max_open_files {
+                    let (_, sink) = self.opened.remove(0);
+                    sink.close()?;
+                }
+                // the parts sharing the same file are appended
+                let append = !self.seen.insert(path.to_owned());
+                if !append {
+                    self.written.push(path.to_owned());
+                }
+                let sink = PartSink::open(&self.staging_path(path), append, self.opts.gzip)?;
+                self.opened.push((path.to_owned(), sink));
+                self.opened.len() - 1
+            }
+        };
+        Ok(self.opened[i].1.writer())
+    }
+
+    /// Write part numbered as `index` with `key` by copying all text from
+    /// `r`. Return the path of the written file.
+    pub fn write_part<T: Read + ?Sized>(&mut self, index: usize, key: Option<&str>, r: &mut T) -> Result<PathBuf> {
+        let path = self.opts.file_path(index, key)?;
+        let w = self.writer_for(&path)?;
+        std::io::copy(r, w).with_context(|| format!("Failed to write part {index} into {path:?}"))?;
+        Ok(path)
+    }
+
+    /// Close all files, and move them into the final destinations in atomic
+    /// mode. Return paths of all written files.
+    pub fn finish(mut self) -> Result<Vec<PathBuf>> {
+        for (_, sink) in self.opened.drain(..) {
+            sink.close()?;
+        }
+        if self.opts.atomic {
+            for path in self.written.iter() {
+                std::fs::rename(self.staging_path(path), path)
+                    .with_context(|| format!("Failed to move part file into {path:?}"))?;
+            }
+        }
+        self.finished = true;
+        Ok(std::mem::take(&mut self.written))
+    }
+}
+
+impl Drop for PartFiles {
+    // remove half-written files on failure
+    fn drop(&mut self) {
+        if !self.finished && self.opts.atomic {
+            for (_, sink) in self.opened.drain(..) {
+                let _ = sink.close();
+            }
+            for path in self.written.iter() {
+                let _ = std::fs::remove_file(self.staging_path(path));
+            }
+        }
+    }
+}
+// c85d2f19 ends here
+
+// [[file:../parser.note::5b7a0f34][5b7a0f34]]
+/// Write each part in `parts` into its own file using options in `opts`.
+/// The key of each part for `{key}` in file name template is extracted with
+/// `key`. Return paths of all written files.
+pub fn split_parts<I, S, F>(parts: I, opts: &SplitOptions, mut key: F) -> Result<Vec<PathBuf>>
+where
+    I: IntoIterator<Item = S>,
+    S: AsRef<str>,
+    F: FnMut(&str) -> Option<String>,
+{
+    let mut files = PartFiles::new(opts);
+    for (i, part) in parts.into_iter().enumerate() {
+        let part = part.as_ref();
+        let k = if opts.need_key() { key(part) } else { None };
+        files.write_part(i, k.as_deref(), &mut part.as_bytes())?;
+    }
+    files.finish()
+}
+
+impl crate::GrepReader {
+    /// Write each marked frame into its own file using options in `opts`.
+    /// The key of each frame for `{key}` in file name template is extracted
+    /// with `key`. Return paths of all written files.
+    ///
+    /// # NOTE
+    /// - The frames are copied in raw bytes, unless `{key}` is required.
+    pub fn split_frames<F>(&mut self, opts: &SplitOptions, mut key: F) -> Result<Vec<PathBuf>>
+    where
+        F: FnMut(&str) -> Option<String>,
+    {
+        let mut files = PartFiles::new(opts);
+        for i in 0..self.num_markers() {
+            let (beg, end) = self.frame_span(i)?;
+            self.goto_marker(i)?;
+            let mut r = Read::by_ref(self.get_mut()).take(end - beg);
+            if opts.need_key() {
+                let mut s = String::new();
+                r.read_to_string(&mut s)?;
+                files.write_part(i, key(&s).as_deref(), &mut s.as_bytes())?;
+            } else {
+                files.write_part(i, None, &mut r)?;
+            }
+        }
+        files.finish()
+    }
+}
+// 5b7a0f34 ends here
+
+// [[file:../parser.note::e9d04a6b][e9d04a6b]]
+#[test]
+fn test_render_template() -> Result<()> {
+    assert_eq!(render_template("frame_{index:05}.xyz", 12, None)?, "frame_00012.xyz");
+    assert_eq!(render_template("step_{key}.dump", 12, Some("100"))?, "step_100.dump");
+    assert_eq!(render_template("{key:4}-{index}", 1, Some("ab"))?, "  ab-1");
+    assert!(render_template("step_{key}.dump", 12, None).is_err());
+    assert!(render_template("step_{keys}.dump", 12, None).is_err());
+    assert!(render_template("step_{key.dump", 12, None).is_err());
+
+    Ok(())
+}
+
+#[test]
+fn test_split_files() -> Result<()> {
+    use crate::GrepReader;
+    use crate::TextReader;
+
+    let dir = tempfile::tempdir()?;
+
+    // CRLF line endings are kept as they are
+    let f = "./tests/files/multi.xyz";
+    let template = dir.path().join("xyz/frame_{index:05}.xyz");
+    let opts = SplitOptions::new(template.to_str().unwrap());
+    let mut reader = GrepReader::try_from_path(f.as_ref())?;
+    reader.mark(r"^\s*\d+\s*$", None)?;
+    let files = reader.split_frames(&opts, |_| None)?;
+    assert_eq!(files.len(), 6);
+    let txt: String = files.iter().map(|p| read_file(p).unwrap()).collect();
+    assert_eq!(txt, read_file(f)?);
+    // no temporary files left
+    assert_eq!(std::fs::read_dir(dir.path().join("xyz"))?.count(), 6);
+
+    // split by key, with two parts in one file
+    let f = "./tests/files/lammps-test.dump";
+    let reader = TextReader::try_from_path(f.as_ref())?;
+    let parts = reader.partitions_preceded(|line| line.starts_with("ITEM: TIMESTEP"));
+    let template = dir.path().join("step_{key}.dump");
+    let opts = SplitOptions::new(template.to_str().unwrap()).max_open_files(1);
+    let key = |part: &str| {
+        let step: usize = part.lines().nth(1)?.trim().parse().ok()?;
+        Some((step / 200).to_string())
+    };
+    let files = split_parts(parts, &opts, key)?;
+    assert_eq!(files.len(), 2);
+    assert!(files[0].ends_with("step_0.dump"));
+    assert_eq!(read_file(&files[0])?.lines().count(), 1092);
+
+    // gzip output
+    let template = dir.path().join("step_{index}.dump");
+    let opts = SplitOptions::new(template.to_str().unwrap()).gzip(true);
+    let files = split_parts(["part 1\n", "part 2\n"], &opts, |_| None)?;
+    assert!(files[1].ends_with("step_1.dump.gz"));
+    let mut out = String::new();
+    flate2::read::MultiGzDecoder::new(File::open(&files[1])?).read_to_string(&mut out)?;
+    assert_eq!(out, "part 2\n");
+    // appended as multi-member gzip file
+    let template = dir.path().join("gz_{key}.txt");
+    let opts = SplitOptions::new(template.to_str().unwrap()).gzip(true).max_open_files(1);
+    let files = split_parts(["a 1\n", "b 1\n", "a 2\n"], &opts, |part: &str| part.get(..1).map(|k| k.to_owned()))?;
+    assert_eq!(files.len(), 2);
+    let mut out = String::new();
+    flate2::read::MultiGzDecoder::new(File::open(&files[0])?).read_to_string(&mut out)?;
+    assert_eq!(out, "a 1\na 2\n");
+
+    // no half-written files on failure
+    let template = dir.path().join("fail_{key}.txt");
+    let opts = SplitOptions::new(template.to_str().unwrap());
+    let key = |part: &str| part.starts_with("ok").then(|| part.trim().to_owned());
+    assert!(split_parts(["ok1", "bad"], &opts, key).is_err());
+    assert!(!dir.path().join("fail_ok1.txt").exists());
+    assert!(!dir.path().join(".fail_ok1.txt.tmp").exists());
+
+    Ok(())
+}
+// e9d04a6b ends here
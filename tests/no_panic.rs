@@ -0,0 +1,204 @@
+// [[file:../parser.note::e72b6c0d][e72b6c0d]]
+//! Feed public entry points with pseudo-random malformed input, checking
+//! that errors are returned instead of panics. The input is generated from
+//! fixed seeds, so any failure is reproducible.
+
+use gchemol_parser::limits::Limits;
+use gchemol_parser::parsers::*;
+use gchemol_parser::partition::*;
+use gchemol_parser::{GrepReader, TextReader, TextViewer};
+use gut::prelude::*;
+
+const NROUNDS: u64 = 200;
+
+/// A xorshift generator, good enough for generating test input.
+struct Rng(u64);
+
+impl Rng {
+    fn new(seed: u64) -> Self {
+        Self(seed.wrapping_mul(0x9E37_79B9_7F4A_7C15) | 1)
+    }
+
+    fn next(&mut self) -> u64 {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+        self.0
+    }
+
+    fn below(&mut self, n: usize) -> usize {
+        (self.next() % n.max(1) as u64) as usize
+    }
+
+    fn pick<'a>(&mut self, items: &[&'a str]) -> &'a str {
+        items[self.below(items.len())]
+    }
+}
+
+/// Generate text of random lines mixed with counts, numbers, blank lines, and
+/// odd characters.
+fn random_text(rng: &mut Rng) -> String {
+    let pieces = [
+        "3", "0", "-1", "99999999999999999999", "C 0.0 1.0 2.0", "1.5D+03", "nan", " ", "", "\r", "\0", "$$$$",
+        "ITEM: TIMESTEP", "END", "é中文", "\t\t", "1 2 3", "+", "e", "1e", "--5",
+    ];
+    let mut txt = String::new();
+    for _ in 0..rng.below(30) {
+        for _ in 0..rng.below(4) {
+            txt += rng.pick(&pieces);
+            txt += rng.pick(&[" ", "", "  "]);
+        }
+        txt += rng.pick(&["\n", "\n", "\r\n", ""]);
+    }
+    txt
+}
+
+/// A partitioner returning arbitrary actions.
+struct Chaos(std::cell::Cell<u64>);
+
+impl ReadPart for Chaos {
+    fn read_next(&self, context: ReadContext) -> ReadAction {
+        let mut rng = Rng::new(self.0.get());
+        self.0.set(rng.next());
+        let n = context.number_of_lines();
+        let _ = context.try_line(rng.below(n + 2));
+        match rng.below(5) {
+            0 => ReadAction::Need(rng.below(3)),
+            1 => ReadAction::Done(rng.below(n + 3)),
+            2 => ReadAction::Skip(rng.below(n + 3)),
+            3 => ReadAction::Fail(PartError::new(rng.below(n + 2), "chaos")),
+            _ => ReadAction::Stop,
+        }
+    }
+
+    fn n_stride(&self) -> usize {
+        self.0.get() as usize % 3
+    }
+}
+
+fn check_partitions<P: ReadPart>(txt: &str, p: P, limits: Option<Limits>) {
+    let mut reader = TextReader::from_str(txt);
+    if let Some(limits) = limits {
+        reader = reader.with_limits(limits);
+    }
+    let nlines = txt.split_inclusive('\n').count();
+    let mut parts = reader.partitions(p);
+    let mut n = 0;
+    while parts.next().is_some() {
+        n += 1;
+        // each part takes at least one line
+        assert!(n <= nlines, "more parts than lines in {txt:?}");
+    }
+    let _ = parts.error();
+}
+
+#[test]
+fn test_no_panic_viewer() {
+    for seed in 0..NROUNDS {
+        let mut rng = Rng::new(seed);
+        let txt = random_text(&mut rng);
+        let mut view = TextViewer::from_str(&txt);
+        let m = view.num_lines();
+        for _ in 0..20 {
+            let n = rng.below(m + 3);
+            let _ = view.try_goto_line(n);
+            let _ = view.try_goto_next_line();
+            let _ = view.try_goto_previous_line();
+            let _ = view.try_peek_line(rng.below(m + 3));
+            let _ = view.try_peek_lines(rng.below(m + 3), rng.below(m + 3));
+            let _ = view.try_selection(rng.below(m + 3));
+            let _ = view.try_column_selection(rng.below(m + 3), rng.below(20), rng.below(20));
+            let _ = view.search_forward(rng.pick(&["^3", "END", "(", "$"]));
+            let _ = view.search_backward(rng.pick(&["^C", "[", "^$"]));
+        }
+    }
+}
+
+#[test]
+fn test_no_panic_partitions() {
+    for seed in 0..NROUNDS {
+        let mut rng = Rng::new(seed);
+        let txt = random_text(&mut rng);
+        check_partitions(&txt, FixedLines(rng.below(4)), None);
+        check_partitions(&txt, CountHeader::xyz(), None);
+        check_partitions(&txt, CountHeader { line: rng.below(3), extra: rng.below(3) }, None);
+        check_partitions(&txt, BlankSeparated, None);
+        check_partitions(&txt, Delimited("$$$$".into()), None);
+        check_partitions(&txt, HeaderFooter(|l: &str| l.starts_with("ITEM"), |l: &str| l.starts_with("END")), None);
+        check_partitions(&txt, Repeat::new(CountHeader::xyz(), rng.below(3)), None);
+        check_partitions(&txt, Preceded(|l: &str| l.starts_with('3')), None);
+        check_partitions(&txt, Terminated(|l: &str| l.trim().is_empty()), None);
+        check_partitions(&txt, Chaos(rng.next().into()), None);
+
+        let limits = Limits::new()
+            .max_line_length(rng.below(40))
+            .max_part_size(rng.below(200))
+            .max_buffered(rng.below(400))
+            .detect_binary(true);
+        check_partitions(&txt, CountHeader::xyz(), Some(limits));
+        check_partitions(&txt, Chaos(rng.next().into()), Some(limits));
+
+        let mut reader = TextReader::from_str(&txt).with_limits(limits);
+        let _ = reader.for_each_line(|_| Ok(()));
+    }
+}
+
+#[test]
+fn test_no_panic_grep() -> Result<()> {
+    let dir = gut::fs::tempfile::tempdir()?;
+    for seed in 0..NROUNDS / 4 {
+        let mut rng = Rng::new(seed);
+        let txt = random_text(&mut rng);
+        let f = dir.path().join(format!("{seed}.txt"));
+        std::fs::write(&f, &txt)?;
+
+        let mut reader = GrepReader::try_from_path(&f)?;
+        let _ = reader.read_until_next_marker(&mut String::new());
+        let m = reader.mark(rng.pick(&[r"^\s*\d+\s*$", "END", "^$"]), None)?;
+        for _ in 0..10 {
+            let _ = reader.goto_marker(rng.below(m + 2));
+            let _ = reader.read_until_next_marker(&mut String::new());
+            let _ = reader.goto_next_marker();
+            let _ = reader.read_lines(rng.below(5), &mut String::new());
+        }
+        reader.goto_end();
+        let _ = reader.read_until_next_marker(&mut String::new());
+        let a = rng.below(m + 2) as isize - 1;
+        if let Ok(frames) = reader.read_frames(a.., rng.below(3)) {
+            for _ in frames {}
+        }
+        if let Ok(index) = reader.index() {
+            let _ = index.read_frame(rng.below(m + 2), &mut String::new());
+            let _ = index.frame_span(rng.below(m + 2));
+        }
+    }
+    Ok(())
+}
+
+#[test]
+fn test_no_panic_parsers() {
+    for seed in 0..NROUNDS {
+        let mut rng = Rng::new(seed);
+        let txt = random_text(&mut rng);
+        for line in txt.lines() {
+            let _ = read_line.parse_peek(line);
+            let _ = rest_line.parse_peek(line);
+            let _ = read_usize_many.parse_peek(line);
+            let _ = read_double_many.parse_peek(line);
+            let _ = xyz_array.parse_peek(line);
+            let _ = signed_integer.parse_peek(line);
+            let _ = parse_float(line);
+            let _ = rest_line_or_eof.parse_peek(line);
+            let _ = read_usize_many_or_eof.parse_peek(line);
+            let _ = read_double_many_or_eof.parse_peek(line);
+            let _ = fortran_double_with(',').parse_peek(line);
+            let _ = fortran_xyz_array.parse_peek(line);
+            let _ = read_fortran_double_many.parse_peek(line);
+            let _ = complex.parse_peek(line);
+            let _ = vector.parse_peek(line);
+        }
+        let _ = tensor3x3.parse_peek(&txt);
+        let _ = gchemol_parser::infer::infer_frame_layouts(&txt);
+    }
+}
+// e72b6c0d ends here